@@ -0,0 +1,77 @@
+use alloy_primitives::{hex, Address, U160};
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// An inclusive range of addresses by their value as a 160-bit integer,
+/// given as `LO..HI` in hex, e.g. `0x1..0xffffffffff` for addresses just
+/// above zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct AddressRange {
+    pub low: Address,
+    pub high: Address,
+}
+
+impl AddressRange {
+    /// Whether `address` lies within the range.
+    pub fn contains(&self, address: &Address) -> bool {
+        // big-endian bytes compare like the integers they encode
+        self.low <= *address && *address <= self.high
+    }
+
+    /// The number of zero nibbles every address of the range starts with,
+    /// as a leading zero nibbles threshold for the kernel to pre-filter
+    /// addresses with, if there are any.
+    pub fn leading_zero_nibbles(&self) -> Option<u8> {
+        let digits = hex::encode(self.high);
+        let nibbles = digits.len() - digits.trim_start_matches('0').len();
+        (nibbles > 0).then(|| nibbles.min(40) as u8)
+    }
+}
+
+/// The address of a hex number of up to 40 digits.
+fn parse_value(s: &str, range: &str) -> Result<Address, String> {
+    let digits = s.trim();
+    let digits = digits.strip_prefix("0x").unwrap_or(digits);
+    if digits.is_empty() || digits.len() > 40 {
+        return Err(format!(
+            "Address range bound `{s}` in `{range}` must have between 1 and 40 hex digits"
+        ));
+    }
+    let bytes = hex::decode(format!("{digits:0>40}"))
+        .map_err(|_| format!("Address range bound `{s}` in `{range}` isn't hex"))?;
+    Ok(Address::from_slice(&bytes))
+}
+
+impl FromStr for AddressRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (low, high) = s
+            .split_once("..")
+            .ok_or_else(|| format!("Address range `{s}` must be given as LO..HI"))?;
+        let low = parse_value(low, s)?;
+        let high = parse_value(high, s)?;
+        if low > high {
+            return Err(format!("Address range `{s}` ends before it starts"));
+        }
+        Ok(AddressRange { low, high })
+    }
+}
+
+impl TryFrom<String> for AddressRange {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for AddressRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let low = U160::from_be_bytes(self.low.0 .0);
+        let high = U160::from_be_bytes(self.high.0 .0);
+        write!(f, "{low:#x}..{high:#x}")
+    }
+}
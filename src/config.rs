@@ -0,0 +1,745 @@
+use crate::{
+    AddressPrefix, AddressRange, Config, Create3Variant, MatchByte, Mode, NonceEndian,
+    NotifyOverflow, OutputFormat, OutputTemplate, SaltMode, SearchState, MIN_SMALL_WORK_SIZE,
+    MIN_WORK_SIZE,
+};
+use alloy_primitives::{keccak256, Address, Bytes, FixedBytes};
+use regex::Regex;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Builds a [`Config`] with the same defaults as the command line, refusing to
+/// build one that can't be searched.
+#[derive(Default)]
+pub struct ConfigBuilder {
+    factory: Option<Address>,
+    owner: Option<Address>,
+    init_code_hashes: Vec<FixedBytes<32>>,
+    init_codes: Vec<Bytes>,
+    work_size: Option<u32>,
+    gpu_devices: Vec<u8>,
+    allow_no_gpu_devices: bool,
+    max_create3_nonce: Option<u8>,
+    proxy_nonces: Option<Vec<u8>>,
+    allow_no_threshold: bool,
+    leading_zeroes_threshold: Option<u8>,
+    total_zeroes_threshold: Option<u8>,
+    leading_zero_nibbles_threshold: Option<u8>,
+    repeat_run: Option<u8>,
+    prefixes: Vec<AddressPrefix>,
+    match_bytes: Vec<MatchByte>,
+    mimic: Option<(Address, u8)>,
+    checksum_regex: Option<Regex>,
+    min_uppercase: Option<u8>,
+    address_range: Option<AddressRange>,
+    output_file: Option<String>,
+    output_format: OutputFormat,
+    output_template: Option<OutputTemplate>,
+    tag: Option<String>,
+    durable: bool,
+    stop_flag: Option<Arc<AtomicBool>>,
+    state_file: Option<String>,
+    reward_file: Option<String>,
+    quiet: bool,
+    nonce_endian: NonceEndian,
+    verbose: bool,
+    max_results: Option<usize>,
+    min_reward_score: Option<f64>,
+    stop_at_reward: Option<String>,
+    timeout: Option<Duration>,
+    summary_file: Option<String>,
+    stats_file: Option<String>,
+    stats_interval: Option<Duration>,
+    throttle: Option<u8>,
+    seed: Option<u64>,
+    mode: Mode,
+    create3_variant: Create3Variant,
+    salt_prefix: Option<Bytes>,
+    free_salt: bool,
+    salt_bytes: Option<u8>,
+    salt_mode: SaltMode,
+    salt_start: Option<u64>,
+    start: Option<(Bytes, u32)>,
+    nonce_stride: Option<u32>,
+    metrics_addr: Option<SocketAddr>,
+    ws_addr: Option<SocketAddr>,
+    require_all: bool,
+    auto_clamp: bool,
+    allow_small_work_size: bool,
+    autotune: bool,
+    max_compute_units: Option<u32>,
+    self_test: bool,
+    notify_threshold: Option<u8>,
+    notify_cmd: Option<String>,
+    notify_concurrency: Option<u8>,
+    notify_overflow: NotifyOverflow,
+}
+
+impl Config {
+    /// Starts building a Config.
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+}
+
+impl ConfigBuilder {
+    pub fn factory(mut self, factory: Address) -> Self {
+        self.factory = Some(factory);
+        self
+    }
+
+    pub fn owner(mut self, owner: Address) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    /// Adds an initcode hash to search, can be called repeatedly.
+    pub fn init_code_hash(mut self, init_code_hash: FixedBytes<32>) -> Self {
+        self.init_code_hashes.push(init_code_hash);
+        self
+    }
+
+    /// Adds an initcode to search, can be called repeatedly. If initcode
+    /// hashes are given too, it must hash to one of them.
+    pub fn init_code(mut self, init_code: &[u8]) -> Self {
+        self.init_codes.push(Bytes::copy_from_slice(init_code));
+        self
+    }
+
+    pub fn init_code_hashes(mut self, init_code_hashes: Vec<FixedBytes<32>>) -> Self {
+        self.init_code_hashes = init_code_hashes;
+        self
+    }
+
+    /// Defaults to `0x4000000`.
+    pub fn work_size(mut self, work_size: u32) -> Self {
+        self.work_size = Some(work_size);
+        self
+    }
+
+    /// At least one, unless [`ConfigBuilder::allow_no_gpu_devices`].
+    pub fn gpu_devices(mut self, gpu_devices: Vec<u8>) -> Self {
+        self.gpu_devices = gpu_devices;
+        self
+    }
+
+    /// Builds a Config without GPU devices, for running on the CPU or
+    /// without searching.
+    pub fn allow_no_gpu_devices(mut self, allow_no_gpu_devices: bool) -> Self {
+        self.allow_no_gpu_devices = allow_no_gpu_devices;
+        self
+    }
+
+    /// Defaults to 32.
+    pub fn max_create3_nonce(mut self, max_create3_nonce: u8) -> Self {
+        self.max_create3_nonce = Some(max_create3_nonce);
+        self
+    }
+
+    /// Create1 nonces of the deploy proxy to search instead of the range up
+    /// to `max_create3_nonce`, see [`Config::proxy_nonces`].
+    pub fn proxy_nonces(mut self, proxy_nonces: Vec<u8>) -> Self {
+        self.proxy_nonces = Some(proxy_nonces);
+        self
+    }
+
+    /// Builds a Config without a threshold, for benchmarking or verifying
+    /// rather than searching.
+    pub fn allow_no_threshold(mut self, allow_no_threshold: bool) -> Self {
+        self.allow_no_threshold = allow_no_threshold;
+        self
+    }
+
+    pub fn leading_zeroes_threshold(mut self, threshold: u8) -> Self {
+        self.leading_zeroes_threshold = Some(threshold);
+        self
+    }
+
+    pub fn total_zeroes_threshold(mut self, threshold: u8) -> Self {
+        self.total_zeroes_threshold = Some(threshold);
+        self
+    }
+
+    /// Between 1 and 40 nibbles.
+    pub fn leading_zero_nibbles_threshold(mut self, threshold: u8) -> Self {
+        self.leading_zero_nibbles_threshold = Some(threshold);
+        self
+    }
+
+    /// Between 1 and 40 nibbles.
+    pub fn repeat_run(mut self, repeat_run: u8) -> Self {
+        self.repeat_run = Some(repeat_run);
+        self
+    }
+
+    /// Addresses starting with any of them, see [`Config::prefixes`].
+    pub fn prefixes(mut self, prefixes: Vec<AddressPrefix>) -> Self {
+        self.prefixes = prefixes;
+        self
+    }
+
+    /// At most one per position, see [`Config::match_bytes`].
+    pub fn match_bytes(mut self, match_bytes: Vec<MatchByte>) -> Self {
+        self.match_bytes = match_bytes;
+        self
+    }
+
+    /// Adds match bytes for the first and last `nibbles` hex digits of
+    /// `address`, see [`MatchByte::mimic`].
+    pub fn mimic(mut self, address: Address, nibbles: u8) -> Self {
+        self.mimic = Some((address, nibbles));
+        self
+    }
+
+    /// Post-filters solutions on their checksummed address, see
+    /// [`Config::checksum_regex`].
+    pub fn checksum_regex(mut self, checksum_regex: Regex) -> Self {
+        self.checksum_regex = Some(checksum_regex);
+        self
+    }
+
+    /// Between 1 and 40 letters, post-filters solutions like
+    /// [`Config::checksum_regex`].
+    pub fn min_uppercase(mut self, min_uppercase: u8) -> Self {
+        self.min_uppercase = Some(min_uppercase);
+        self
+    }
+
+    /// Post-filters solutions on their value, see [`Config::address_range`].
+    pub fn address_range(mut self, address_range: AddressRange) -> Self {
+        self.address_range = Some(address_range);
+        self
+    }
+
+    /// Defaults to `efficient_addresses.txt`.
+    pub fn output_file(mut self, output_file: impl Into<String>) -> Self {
+        self.output_file = Some(output_file.into());
+        self
+    }
+
+    pub fn output_format(mut self, output_format: OutputFormat) -> Self {
+        self.output_format = output_format;
+        self
+    }
+
+    /// Only applies to the `text` format.
+    pub fn output_template(mut self, output_template: OutputTemplate) -> Self {
+        self.output_template = Some(output_template);
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+
+    pub fn durable(mut self, durable: bool) -> Self {
+        self.durable = durable;
+        self
+    }
+
+    pub fn stop_flag(mut self, stop_flag: Arc<AtomicBool>) -> Self {
+        self.stop_flag = Some(stop_flag);
+        self
+    }
+
+    pub fn state_file(mut self, state_file: impl Into<String>) -> Self {
+        self.state_file = Some(state_file.into());
+        self
+    }
+
+    pub fn reward_file(mut self, reward_file: impl Into<String>) -> Self {
+        self.reward_file = Some(reward_file.into());
+        self
+    }
+
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Defaults to [`NonceEndian::Le`].
+    pub fn nonce_endian(mut self, nonce_endian: NonceEndian) -> Self {
+        self.nonce_endian = nonce_endian;
+        self
+    }
+
+    /// At least 1.
+    pub fn max_results(mut self, max_results: usize) -> Self {
+        self.max_results = Some(max_results);
+        self
+    }
+
+    pub fn min_reward_score(mut self, min_reward_score: f64) -> Self {
+        self.min_reward_score = Some(min_reward_score);
+        self
+    }
+
+    pub fn stop_at_reward(mut self, stop_at_reward: impl Into<String>) -> Self {
+        self.stop_at_reward = Some(stop_at_reward.into());
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn summary_file(mut self, summary_file: impl Into<String>) -> Self {
+        self.summary_file = Some(summary_file.into());
+        self
+    }
+
+    pub fn stats_file(mut self, stats_file: impl Into<String>) -> Self {
+        self.stats_file = Some(stats_file.into());
+        self
+    }
+
+    /// Defaults to one second.
+    pub fn stats_interval(mut self, stats_interval: Duration) -> Self {
+        self.stats_interval = Some(stats_interval);
+        self
+    }
+
+    /// Defaults to 98.
+    pub fn throttle(mut self, throttle: u8) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Non-default variants supply their deploy proxy's initcode hash if none
+    /// is given.
+    pub fn create3_variant(mut self, create3_variant: Create3Variant) -> Self {
+        self.create3_variant = create3_variant;
+        self
+    }
+
+    /// Makes the owner optional, as it's no longer part of the salt.
+    pub fn salt_prefix(mut self, salt_prefix: Bytes) -> Self {
+        self.salt_prefix = Some(salt_prefix);
+        self
+    }
+
+    /// Draws the first 20 bytes of the salt at random instead of using the
+    /// owner, only for factories that don't bind salts to the caller.
+    pub fn free_salt(mut self, free_salt: bool) -> Self {
+        self.free_salt = free_salt;
+        self
+    }
+
+    /// Between 4 and 8 bytes, defaults to 4.
+    pub fn salt_bytes(mut self, salt_bytes: u8) -> Self {
+        self.salt_bytes = Some(salt_bytes);
+        self
+    }
+
+    pub fn salt_mode(mut self, salt_mode: SaltMode) -> Self {
+        self.salt_mode = salt_mode;
+        self
+    }
+
+    /// Starts the search at `nonce` of `salt`, which must have `salt_bytes`
+    /// bytes, on a single device.
+    pub fn start(mut self, salt: Bytes, nonce: u32) -> Self {
+        self.start = Some((salt, nonce));
+        self
+    }
+
+    /// Defaults to 0, must fit in `salt_bytes` bytes.
+    pub fn salt_start(mut self, salt_start: u64) -> Self {
+        self.salt_start = Some(salt_start);
+        self
+    }
+
+    /// Only applies to random salts, see [`Config::nonce_stride`].
+    pub fn nonce_stride(mut self, nonce_stride: u32) -> Self {
+        self.nonce_stride = Some(nonce_stride);
+        self
+    }
+
+    pub fn metrics_addr(mut self, metrics_addr: SocketAddr) -> Self {
+        self.metrics_addr = Some(metrics_addr);
+        self
+    }
+
+    pub fn ws_addr(mut self, ws_addr: SocketAddr) -> Self {
+        self.ws_addr = Some(ws_addr);
+        self
+    }
+
+    pub fn require_all(mut self, require_all: bool) -> Self {
+        self.require_all = require_all;
+        self
+    }
+
+    pub fn auto_clamp(mut self, auto_clamp: bool) -> Self {
+        self.auto_clamp = auto_clamp;
+        self
+    }
+
+    /// Lowers the minimum work size for development, see
+    /// [`Config::allow_small_work_size`].
+    pub fn allow_small_work_size(mut self, allow_small_work_size: bool) -> Self {
+        self.allow_small_work_size = allow_small_work_size;
+        self
+    }
+
+    pub fn autotune(mut self, autotune: bool) -> Self {
+        self.autotune = autotune;
+        self
+    }
+
+    /// Caps the compute units a batch occupies at a time, see
+    /// [`Config::max_compute_units`].
+    pub fn max_compute_units(mut self, max_compute_units: u32) -> Self {
+        self.max_compute_units = Some(max_compute_units);
+        self
+    }
+
+    pub fn self_test(mut self, self_test: bool) -> Self {
+        self.self_test = self_test;
+        self
+    }
+
+    pub fn notify_threshold(mut self, notify_threshold: u8) -> Self {
+        self.notify_threshold = Some(notify_threshold);
+        self
+    }
+
+    pub fn notify_cmd(mut self, notify_cmd: impl Into<String>) -> Self {
+        self.notify_cmd = Some(notify_cmd.into());
+        self
+    }
+
+    /// At least 1, defaults to 2.
+    pub fn notify_concurrency(mut self, notify_concurrency: u8) -> Self {
+        self.notify_concurrency = Some(notify_concurrency);
+        self
+    }
+
+    pub fn notify_overflow(mut self, notify_overflow: NotifyOverflow) -> Self {
+        self.notify_overflow = notify_overflow;
+        self
+    }
+
+    /// Checks that the search is possible and builds the Config.
+    pub fn build(mut self) -> Result<Config, String> {
+        if let Some((address, nibbles)) = self.mimic {
+            self.match_bytes
+                .extend(MatchByte::mimic(&address, nibbles)?);
+        }
+        let factory = self.factory.ok_or("Must specify the factory address")?;
+
+        if let Some(prefix) = &self.salt_prefix {
+            if prefix.len() > 20 {
+                return Err(format!(
+                    "Salt prefix can be at most 20 bytes, got {}",
+                    prefix.len()
+                ));
+            }
+            if self.free_salt {
+                return Err(
+                    "Free salts are drawn at random, they can't have a salt prefix".to_string(),
+                );
+            }
+        }
+        let owner = match self.owner {
+            Some(owner) => owner,
+            None if self.salt_prefix.is_some() || self.free_salt => Address::ZERO,
+            None => return Err("Must specify the owner address".to_string()),
+        };
+
+        if self.create3_variant != Create3Variant::Default {
+            if self.mode != Mode::Create3 {
+                return Err("A create3 variant can only be used in create3 mode".to_string());
+            }
+            if self.salt_prefix.is_some() {
+                return Err("A create3 variant binds salts to the owner, it can't be used with a salt prefix".to_string());
+            }
+            if self.free_salt {
+                return Err(
+                    "A create3 variant binds salts to the owner, it can't be used with free salts"
+                        .to_string(),
+                );
+            }
+        }
+
+        let mut init_code_hashes = self.init_code_hashes;
+        let hashed: Vec<FixedBytes<32>> = self.init_codes.iter().map(keccak256).collect();
+        if init_code_hashes.is_empty() {
+            init_code_hashes = hashed;
+        } else if let Some(hash) = hashed.iter().find(|hash| !init_code_hashes.contains(hash)) {
+            return Err(format!(
+                "The given initcode hashes to {hash}, which isn't one of the given initcode hashes"
+            ));
+        }
+        if init_code_hashes.is_empty() {
+            init_code_hashes.extend(self.create3_variant.proxy_init_code_hash());
+        }
+        if init_code_hashes.is_empty() {
+            return Err("Must specify at least one initcode hash".to_string());
+        }
+
+        let no_threshold = self.leading_zeroes_threshold.is_none()
+            && self.total_zeroes_threshold.is_none()
+            && self.leading_zero_nibbles_threshold.is_none()
+            && self.repeat_run.is_none()
+            && self.prefixes.is_empty()
+            && self.match_bytes.is_empty();
+        if !self.allow_no_threshold
+            && no_threshold
+            && self.checksum_regex.is_none()
+            && self.min_uppercase.is_none()
+            && self.address_range.is_none()
+        {
+            return Err("Must specify at least one threshold: total zeros, leading zeros, leading zero nibbles, repeat run, prefix list, match bytes, checksum regex, min uppercase or address range".to_string());
+        }
+        // the kernel pre-filters the address range by its leading zeros
+        let leading_zero_nibbles_threshold = match self.address_range {
+            Some(range) if no_threshold => range.leading_zero_nibbles(),
+            _ => self.leading_zero_nibbles_threshold,
+        };
+        for (i, byte) in self.match_bytes.iter().enumerate() {
+            if self.match_bytes[..i]
+                .iter()
+                .any(|other| other.position == byte.position)
+            {
+                return Err(format!("Byte {} is matched more than once", byte.position));
+            }
+        }
+        if let Some(zeros) = self.leading_zeroes_threshold {
+            if !(1..=20).contains(&zeros) {
+                return Err("Leading zeros must be between 1 and 20 bytes".to_string());
+            }
+        }
+        if let Some(zeros) = self.total_zeroes_threshold {
+            if !(1..=20).contains(&zeros) {
+                return Err("Total zeros must be between 1 and 20 bytes".to_string());
+            }
+        }
+        if let Some(min) = self.min_uppercase {
+            if !(1..=40).contains(&min) {
+                return Err("Min uppercase must be between 1 and 40 letters".to_string());
+            }
+        }
+        if let Some(nibbles) = self.leading_zero_nibbles_threshold {
+            if !(1..=40).contains(&nibbles) {
+                return Err("Leading zero nibbles must be between 1 and 40".to_string());
+            }
+        }
+        if let Some(run) = self.repeat_run {
+            if !(1..=40).contains(&run) {
+                return Err("Repeat run must be between 1 and 40 nibbles".to_string());
+            }
+        }
+
+        if self.gpu_devices.is_empty() && !self.allow_no_gpu_devices {
+            return Err("Must specify at least one GPU device".to_string());
+        }
+
+        let salt_bytes = self.salt_bytes.unwrap_or(4);
+        if !(4..=8).contains(&salt_bytes) {
+            return Err(format!(
+                "Salt bytes must be between 4 and 8, got {salt_bytes}"
+            ));
+        }
+        let salt_start = self.salt_start.unwrap_or(0);
+        if salt_bytes < 8 && salt_start >> (8 * salt_bytes) != 0 {
+            return Err(format!(
+                "Salt start {salt_start} doesn't fit in {salt_bytes} salt bytes"
+            ));
+        }
+        let start = match self.start {
+            Some((salt, nonce)) => Some(SearchState::at(&salt, nonce, salt_bytes)?),
+            None => None,
+        };
+        if start.is_some() && self.gpu_devices.len() > 1 {
+            return Err("A start salt and nonce can only be searched from on a single device, give every device its own run".to_string());
+        }
+        match self.nonce_stride {
+            Some(0) => return Err("Nonce stride must be at least 1".to_string()),
+            Some(_) if self.salt_mode == SaltMode::Sequential => {
+                return Err(
+                    "A nonce stride only applies to random salts, sequential searches exhaust every salt"
+                        .to_string(),
+                )
+            }
+            _ => {}
+        }
+
+        if self.output_template.is_some() && self.output_format != OutputFormat::Text {
+            return Err("An output template only applies to the text output format".to_string());
+        }
+
+        let work_size = self.work_size.unwrap_or(0x4000000);
+        let min_work_size = if self.allow_small_work_size {
+            MIN_SMALL_WORK_SIZE
+        } else {
+            MIN_WORK_SIZE
+        };
+        if work_size < min_work_size {
+            return Err(format!("Work size cannot be below {min_work_size:#x}"));
+        }
+        if self.max_compute_units == Some(0) {
+            return Err("Max compute units must be at least 1".to_string());
+        }
+
+        let notify_concurrency = self.notify_concurrency.unwrap_or(2);
+        if notify_concurrency == 0 {
+            return Err("Notify concurrency must be at least 1".to_string());
+        }
+
+        if self.max_results == Some(0) {
+            return Err("Max results must be at least 1".to_string());
+        }
+
+        let max_create3_nonce = self.max_create3_nonce.unwrap_or(32);
+        if !(1..=127).contains(&max_create3_nonce) {
+            return Err(format!(
+                "Max create3 nonce must be between 1 and 127, got {max_create3_nonce}"
+            ));
+        }
+        if let Some(nonces) = &self.proxy_nonces {
+            if nonces.is_empty() {
+                return Err("Must specify at least one proxy nonce".to_string());
+            }
+            for (i, &nonce) in nonces.iter().enumerate() {
+                if !(1..=127).contains(&nonce) {
+                    return Err(format!(
+                        "Proxy nonces must be between 1 and 127, got {nonce}"
+                    ));
+                }
+                if nonces[..i].contains(&nonce) {
+                    return Err(format!("Proxy nonce {nonce} is given more than once"));
+                }
+            }
+        }
+
+        let throttle = self.throttle.unwrap_or(98);
+        if throttle > 100 {
+            return Err(format!("Throttle must be a percentage, got {throttle}"));
+        }
+
+        Ok(Config {
+            factory,
+            owner,
+            init_code_hashes,
+            work_size,
+            gpu_devices: self.gpu_devices,
+            max_create3_nonce,
+            proxy_nonces: self.proxy_nonces,
+            leading_zeroes_threshold: self.leading_zeroes_threshold,
+            total_zeroes_threshold: self.total_zeroes_threshold,
+            leading_zero_nibbles_threshold,
+            repeat_run: self.repeat_run,
+            prefixes: self.prefixes,
+            match_bytes: self.match_bytes,
+            checksum_regex: self.checksum_regex,
+            min_uppercase: self.min_uppercase,
+            address_range: self.address_range,
+            output_file: self
+                .output_file
+                .unwrap_or_else(|| "efficient_addresses.txt".to_string()),
+            output_format: self.output_format,
+            output_template: self.output_template,
+            tag: self.tag,
+            durable: self.durable,
+            stop_flag: self.stop_flag,
+            state_file: self.state_file,
+            reward_file: self.reward_file,
+            quiet: self.quiet,
+            verbose: self.verbose,
+            nonce_endian: self.nonce_endian,
+            max_results: self.max_results,
+            min_reward_score: self.min_reward_score,
+            stop_at_reward: self.stop_at_reward,
+            timeout: self.timeout,
+            summary_file: self.summary_file,
+            stats_file: self.stats_file,
+            stats_interval: self.stats_interval.unwrap_or(Duration::from_secs(1)),
+            throttle,
+            seed: self.seed,
+            mode: self.mode,
+            create3_variant: self.create3_variant,
+            salt_prefix: self.salt_prefix,
+            free_salt: self.free_salt,
+            salt_bytes,
+            salt_mode: self.salt_mode,
+            salt_start,
+            start,
+            nonce_stride: self.nonce_stride,
+            metrics_addr: self.metrics_addr,
+            ws_addr: self.ws_addr,
+            require_all: self.require_all,
+            auto_clamp: self.auto_clamp,
+            allow_small_work_size: self.allow_small_work_size,
+            autotune: self.autotune,
+            max_compute_units: self.max_compute_units,
+            self_test: self.self_test,
+            notify_threshold: self.notify_threshold,
+            notify_cmd: self.notify_cmd,
+            notify_concurrency,
+            notify_overflow: self.notify_overflow,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder() -> ConfigBuilder {
+        Config::builder()
+            .factory(Address::repeat_byte(0x11))
+            .owner(Address::repeat_byte(0x22))
+            .init_code_hash(FixedBytes::repeat_byte(0x33))
+            .gpu_devices(vec![0])
+            .leading_zeroes_threshold(1)
+    }
+
+    #[test]
+    fn max_create3_nonce_fits_a_single_byte_rlp() {
+        let config = builder().max_create3_nonce(127).build().unwrap();
+        assert_eq!(config.max_create3_nonce, 127);
+        assert!(builder().max_create3_nonce(128).build().is_err());
+        assert!(builder().max_create3_nonce(200).build().is_err());
+        assert!(builder().max_create3_nonce(0).build().is_err());
+    }
+
+    #[test]
+    fn zero_thresholds_fit_an_address() {
+        assert!(builder().leading_zeroes_threshold(20).build().is_ok());
+        let err = builder().leading_zeroes_threshold(0).build().err().unwrap();
+        assert_eq!(err, "Leading zeros must be between 1 and 20 bytes");
+        assert!(builder().leading_zeroes_threshold(21).build().is_err());
+
+        assert!(builder().total_zeroes_threshold(20).build().is_ok());
+        let err = builder().total_zeroes_threshold(21).build().err().unwrap();
+        assert_eq!(err, "Total zeros must be between 1 and 20 bytes");
+        assert!(builder().total_zeroes_threshold(0).build().is_err());
+    }
+
+    #[test]
+    fn max_compute_units_is_at_least_one() {
+        assert!(builder().max_compute_units(1).build().is_ok());
+        let err = builder().max_compute_units(0).build().err().unwrap();
+        assert_eq!(err, "Max compute units must be at least 1");
+    }
+}
@@ -0,0 +1,201 @@
+use crate::summary::{RunSummary, ZeroHistogram};
+use crate::{
+    notify::Notifier, output::OutputFile, state::SearchState, stream::SolutionStream, Config, Mode,
+    Solution,
+};
+use rayon::prelude::*;
+use std::io;
+use std::ops::ControlFlow;
+use std::sync::atomic::Ordering;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Searches for salts exactly like [`gpu`](crate::gpu) does, but computes the
+/// hashes on the CPU instead of through OpenCL, appending every solution to
+/// the configured output file.
+///
+/// This is orders of magnitude slower than the GPU miner and is mainly meant
+/// for testing and for low-difficulty targets on machines without OpenCL.
+/// Like the GPU miner, it stops after `max_results` solutions, at a solution
+/// worth `stop_at_reward` or once `timeout` passed if set.
+pub fn cpu(config: Config) -> io::Result<()> {
+    let file = OutputFile::open(&config)?;
+
+    let notifier = Notifier::new(&config);
+    let stream = SolutionStream::new(&config)?;
+    let target = crate::RewardTarget::new(&config)?;
+    let max_results = config.max_results;
+    let mut written = 0;
+    let term = crate::output::status_term(&config.output_file);
+    let result = cpu_with_callback(config, |solution| {
+        if !file.is_stdout() {
+            println!("{solution}");
+        }
+        file.write(solution);
+        notifier.notify(solution);
+        stream.send(solution);
+        written += 1;
+        if target
+            .as_ref()
+            .map_or(false, |target| target.reached(solution))
+        {
+            return ControlFlow::Break(());
+        }
+        crate::enough_results(max_results, written)
+    });
+
+    file.close();
+    term.write_line(&format!("Stopped, total found this run: {written}"))?;
+
+    result
+}
+
+/// Runs the same search as [`cpu`], but invokes `on_solution` for every
+/// solution that is found rather than writing it to the output file. The
+/// search stops once the callback returns [`ControlFlow::Break`] or once the
+/// config's `stop_flag` is raised.
+///
+/// Each batch covers the same `work_size` salts a single kernel invocation on
+/// the GPU would, so both miners report identical solutions for the same salt
+/// and nonce. Every hit of a batch is reported, in nonce order, skipping
+/// salts that are already in the output file.
+pub fn cpu_with_callback<F>(mut config: Config, mut on_solution: F) -> io::Result<()>
+where
+    F: FnMut(&Solution) -> ControlFlow<()>,
+{
+    crate::output::status_term(&config.output_file).write_line(&format!(
+        "Setting up CPU miner using {} threads...",
+        rayon::current_num_threads()
+    ))?;
+    crate::draw_free_salt(&mut config);
+    if let Some(warning) = crate::describe_free_salt(&config) {
+        crate::output::status_term(&config.output_file).write_line(&warning)?;
+    }
+    if let Some(nonces) = crate::describe_nonces(&config) {
+        crate::output::status_term(&config.output_file).write_line(&nonces)?;
+    }
+    tracing::info!(
+        threads = rayon::current_num_threads(),
+        "setting up CPU miner"
+    );
+
+    let rewards = crate::load_rewards(&config)?;
+    let stop_flag = config.stop_flag.clone().unwrap_or_default();
+    let deadline = config.timeout.map(|timeout| Instant::now() + timeout);
+    let mut rng = crate::search_rng(&config, 0);
+    let mut seen = crate::output::read_salts(&config.output_file)?;
+
+    let start_time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64();
+    let mut batches: u64 = 0;
+    let mut found: u64 = 0;
+    let mut histogram = ZeroHistogram::default();
+
+    let mut next = Some(crate::initial_state(&config, &mut rng, 0));
+    'search: loop {
+        let SearchState {
+            salt, mut nonce, ..
+        } = next
+            .take()
+            .unwrap_or_else(|| SearchState::random(&mut rng, config.salt_bytes));
+        let mut batches_on_salt: u32 = 0;
+
+        loop {
+            if stop_flag.load(Ordering::Relaxed)
+                || deadline.map_or(false, |deadline| Instant::now() >= deadline)
+            {
+                break 'search;
+            }
+
+            let hits: Vec<_> = (0..config.work_size)
+                .into_par_iter()
+                .filter_map(|id| {
+                    let create2_nonce = (nonce as u64) << 32 | id as u64;
+                    let create2_salt = crate::create2_salt(&config, &salt, create2_nonce);
+                    let factory_salt = crate::factory_salt(&config, &create2_salt);
+                    // check the hashes in the same order the kernel does
+                    config.init_code_hashes.iter().enumerate().find_map(
+                        |(hash_index, init_code_hash)| {
+                            let deployer = config.factory.create2(factory_salt, init_code_hash);
+                            crate::proxy_nonces(&config)
+                                .into_iter()
+                                .map(u64::from)
+                                .find_map(|create1_nonce| {
+                                    let address = match config.mode {
+                                        Mode::Create3 => deployer.create(create1_nonce),
+                                        Mode::Create2 => deployer,
+                                    };
+                                    let hit = crate::meets_conditions(&config, &address)
+                                        && crate::matches_checksum(&config, &address)
+                                        && crate::in_address_range(&config, &address);
+                                    // flag the conditions met like the kernel does
+                                    hit.then(|| {
+                                        let flags = crate::condition_flags(&config, &address);
+                                        (create2_nonce, create1_nonce, hash_index, flags)
+                                    })
+                                })
+                        },
+                    )
+                })
+                .collect();
+            batches += 1;
+
+            // a sequential search picks up right after this nonce, whether it
+            // had hits or exhausted the salt
+            let searched = SearchState {
+                salt,
+                nonce,
+                cumulative_nonce: batches,
+            };
+            next = crate::next_state(&config, &searched, 1);
+
+            // like the kernel, report every hit of the batch
+            for &(create2_nonce, create1_nonce, hash_index, flags) in &hits {
+                let solution = crate::mk_solution(
+                    &config,
+                    &rewards,
+                    &salt,
+                    create2_nonce,
+                    create1_nonce,
+                    hash_index,
+                    flags,
+                );
+                if !crate::meets_min_reward_score(&config, &solution)
+                    || !seen.insert(solution.create2_salt)
+                {
+                    continue;
+                }
+                crate::log_solution(&solution);
+                found += 1;
+                histogram.record(solution.total_zeros);
+                if on_solution(&solution).is_break() {
+                    break 'search;
+                }
+            }
+            if !hits.is_empty() {
+                break;
+            }
+
+            batches_on_salt += 1;
+            match crate::next_nonce(&config, nonce) {
+                Some(next) if config.nonce_stride != Some(batches_on_salt) => nonce = next,
+                _ => break,
+            }
+        }
+    }
+
+    if !histogram.is_empty() {
+        crate::output::status_term(&config.output_file).write_line(&histogram.to_string())?;
+    }
+
+    if let Some(path) = &config.summary_file {
+        let hashes = batches
+            * config.work_size as u64
+            * crate::create1_nonces(&config) as u64
+            * config.init_code_hashes.len() as u64;
+        RunSummary::new(&config, start_time, batches, hashes, found, &histogram).write(path)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use crate::{default_platform, device_by_idx, mk_kernel_src, Config, Create3Variant, Mode};
+use ocl::{Context, Program};
+
+/// A change to the Config the kernel is built for, applied to a Config
+/// without any condition.
+type Variant = (&'static str, fn(&mut Config));
+
+/// Configs exercising every part of the generated source: each condition on
+/// its own, all of them together either way they're joined, and the modes
+/// and nonce lists that change the hashing around them.
+const VARIANTS: &[Variant] = &[
+    ("leading zeros", |config| {
+        config.leading_zeroes_threshold = Some(1);
+    }),
+    ("total zeros", |config| {
+        config.total_zeroes_threshold = Some(1);
+    }),
+    ("leading zero nibbles", |config| {
+        config.leading_zero_nibbles_threshold = Some(1);
+    }),
+    ("repeat run", |config| config.repeat_run = Some(4)),
+    ("prefixes", set_prefixes),
+    ("prefix bloom filter", |config| {
+        // too many prefixes for the kernel's tables
+        config.prefixes = (0..5000u32)
+            .map(|i| {
+                format!("{:08x}", i.wrapping_mul(0x9e3779b1))
+                    .parse()
+                    .unwrap()
+            })
+            .collect();
+    }),
+    ("prefix bloom filter of mixed lengths", |config| {
+        config.prefixes = (0..5000u32)
+            .map(|i| {
+                let digits = format!("{:08x}", i.wrapping_mul(0x9e3779b1));
+                digits[..2 + i as usize % 7].parse().unwrap()
+            })
+            .collect();
+    }),
+    ("match bytes", set_match_bytes),
+    ("host filters only", |config| config.min_uppercase = Some(1)),
+    ("any condition", set_all_conditions),
+    ("every condition", |config| {
+        set_all_conditions(config);
+        config.require_all = true;
+    }),
+    ("create2", |config| {
+        config.mode = Mode::Create2;
+        config.leading_zeroes_threshold = Some(1);
+    }),
+    ("solmate", |config| {
+        config.create3_variant = Create3Variant::Solmate;
+        config.leading_zeroes_threshold = Some(1);
+    }),
+    ("createx", |config| {
+        config.create3_variant = Create3Variant::CreateX;
+        config.leading_zeroes_threshold = Some(1);
+    }),
+    ("explicit nonces", |config| {
+        config.proxy_nonces = Some(vec![1, 5, 127]);
+        config.leading_zeroes_threshold = Some(1);
+    }),
+    ("8 salt bytes", |config| {
+        config.salt_bytes = 8;
+        config.leading_zeroes_threshold = Some(1);
+    }),
+];
+
+fn set_prefixes(config: &mut Config) {
+    // prefixes of different lengths, so the shorter ones are padded
+    config.prefixes = ["0x00", "0xdead", "0x123"]
+        .iter()
+        .map(|prefix| prefix.parse().unwrap())
+        .collect();
+}
+
+fn set_match_bytes(config: &mut Config) {
+    config.match_bytes = ["0=00", "19=ff"]
+        .iter()
+        .map(|byte| byte.parse().unwrap())
+        .collect();
+}
+
+fn set_all_conditions(config: &mut Config) {
+    config.leading_zeroes_threshold = Some(1);
+    config.total_zeroes_threshold = Some(1);
+    config.leading_zero_nibbles_threshold = Some(1);
+    config.repeat_run = Some(4);
+    set_prefixes(config);
+    set_match_bytes(config);
+}
+
+/// Whether the Config has anything to search for.
+fn has_condition(config: &Config) -> bool {
+    config.leading_zeroes_threshold.is_some()
+        || config.total_zeroes_threshold.is_some()
+        || config.leading_zero_nibbles_threshold.is_some()
+        || config.repeat_run.is_some()
+        || !config.prefixes.is_empty()
+        || !config.match_bytes.is_empty()
+        || config.checksum_regex.is_some()
+        || config.min_uppercase.is_some()
+        || config.address_range.is_some()
+}
+
+/// Leaves the Config without any condition, searching with the default mode
+/// and nonces.
+fn reset(config: &mut Config) {
+    config.leading_zeroes_threshold = None;
+    config.total_zeroes_threshold = None;
+    config.leading_zero_nibbles_threshold = None;
+    config.repeat_run = None;
+    config.prefixes.clear();
+    config.match_bytes.clear();
+    config.checksum_regex = None;
+    config.min_uppercase = None;
+    config.address_range = None;
+    config.require_all = false;
+    config.mode = Mode::default();
+    config.create3_variant = Create3Variant::default();
+    config.proxy_nonces = None;
+    config.salt_bytes = 4;
+}
+
+/// Builds the kernel for `config` and for a range of representative
+/// variants of it on every configured device, printing whether each build
+/// succeeded along with the build error of the ones that didn't. Nothing is
+/// run on the devices, so a software implementation like POCL can check the
+/// generated source where there's no GPU. Returns how many builds failed.
+pub fn check_kernels(mut config: Config) -> ocl::Result<usize> {
+    let platform = default_platform()?;
+    println!("platform: {} ({})", platform.name()?, platform.vendor()?);
+
+    // without a condition there's no kernel as configured, only the variants
+    let mut sources = vec![];
+    if has_condition(&config) {
+        sources.push(("as configured", mk_kernel_src(&config)));
+    }
+    for &(name, apply) in VARIANTS {
+        reset(&mut config);
+        apply(&mut config);
+        sources.push((name, mk_kernel_src(&config)));
+    }
+
+    let mut failed = 0;
+    for &device_idx in &config.gpu_devices {
+        let device = device_by_idx(platform, device_idx)?;
+        println!("device {}: {}", device_idx, device.name()?);
+        let context = Context::builder()
+            .platform(platform)
+            .devices(device)
+            .build()?;
+        for (name, src) in &sources {
+            let build = Program::builder()
+                .devices(device)
+                .src(src.as_str())
+                .build(&context);
+            match build {
+                Ok(_) => println!("  {name}: ok"),
+                Err(err) => {
+                    failed += 1;
+                    println!("  {name}: failed\n{err}");
+                }
+            }
+        }
+    }
+
+    Ok(failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{Address, FixedBytes};
+
+    /// Run with `cargo test -- --ignored` where an OpenCL platform is
+    /// installed, e.g. POCL on a machine without a GPU.
+    #[test]
+    #[ignore = "needs an OpenCL platform, e.g. POCL"]
+    fn every_kernel_variant_builds() {
+        let config = Config::builder()
+            .factory(Address::repeat_byte(0x11))
+            .owner(Address::repeat_byte(0x22))
+            .init_code_hash(FixedBytes::repeat_byte(0x33))
+            .gpu_devices(vec![0])
+            .allow_no_threshold(true)
+            .build()
+            .unwrap();
+        assert_eq!(check_kernels(config).unwrap(), 0);
+    }
+}
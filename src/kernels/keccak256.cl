@@ -131,34 +131,38 @@ a[n + 4] = b[4] ^ ((~b[0]) & b[1]);
 
 #define iteration(x) theta(); rhoPi(); chi(); iota(x);
 
+// the first 23 rounds, shared by the full and the partial permutation
+#define rounds() \
+  iteration(0x0000000000000001); /* iteration 1 */ \
+  iteration(0x0000000000008082); /* iteration 2 */ \
+  iteration(0x800000000000808a); /* iteration 3 */ \
+  iteration(0x8000000080008000); /* iteration 4 */ \
+  iteration(0x000000000000808b); /* iteration 5 */ \
+  iteration(0x0000000080000001); /* iteration 6 */ \
+  iteration(0x8000000080008081); /* iteration 7 */ \
+  iteration(0x8000000000008009); /* iteration 8 */ \
+  iteration(0x000000000000008a); /* iteration 9 */ \
+  iteration(0x0000000000000088); /* iteration 10 */ \
+  iteration(0x0000000080008009); /* iteration 11 */ \
+  iteration(0x000000008000000a); /* iteration 12 */ \
+  iteration(0x000000008000808b); /* iteration 13 */ \
+  iteration(0x800000000000008b); /* iteration 14 */ \
+  iteration(0x8000000000008089); /* iteration 15 */ \
+  iteration(0x8000000000008003); /* iteration 16 */ \
+  iteration(0x8000000000008002); /* iteration 17 */ \
+  iteration(0x8000000000000080); /* iteration 18 */ \
+  iteration(0x000000000000800a); /* iteration 19 */ \
+  iteration(0x800000008000000a); /* iteration 20 */ \
+  iteration(0x8000000080008081); /* iteration 21 */ \
+  iteration(0x8000000000008080); /* iteration 22 */ \
+  iteration(0x0000000080000001); /* iteration 23 */
+
 static inline void keccakf(ulong *a)
 {
   ulong b[5];
   ulong t;
 
-  iteration(0x0000000000000001); // iteration 1
-  iteration(0x0000000000008082); // iteration 2
-  iteration(0x800000000000808a); // iteration 3
-  iteration(0x8000000080008000); // iteration 4
-  iteration(0x000000000000808b); // iteration 5
-  iteration(0x0000000080000001); // iteration 6
-  iteration(0x8000000080008081); // iteration 7
-  iteration(0x8000000000008009); // iteration 8
-  iteration(0x000000000000008a); // iteration 9
-  iteration(0x0000000000000088); // iteration 10
-  iteration(0x0000000080008009); // iteration 11
-  iteration(0x000000008000000a); // iteration 12
-  iteration(0x000000008000808b); // iteration 13
-  iteration(0x800000000000008b); // iteration 14
-  iteration(0x8000000000008089); // iteration 15
-  iteration(0x8000000000008003); // iteration 16
-  iteration(0x8000000000008002); // iteration 17
-  iteration(0x8000000000000080); // iteration 18
-  iteration(0x000000000000800a); // iteration 19
-  iteration(0x800000008000000a); // iteration 20
-  iteration(0x8000000080008081); // iteration 21
-  iteration(0x8000000000008080); // iteration 22
-  iteration(0x0000000080000001); // iteration 23
+  rounds();
 
   // iteration 24 (partial)
 
@@ -191,6 +195,17 @@ static inline void keccakf(ulong *a)
 #undef o
 }
 
+// the complete permutation, for hashes whose whole digest is used rather than
+// just the address in bytes 12..32 that the partial final round computes
+static inline void keccakfFull(ulong *a)
+{
+  ulong b[5];
+  ulong t;
+
+  rounds();
+  iteration(0x8000000080008008); // iteration 24
+}
+
 #define hasTotal(d) ( \
   (!(d[0])) + (!(d[1])) + (!(d[2])) + (!(d[3])) + \
   (!(d[4])) + (!(d[5])) + (!(d[6])) + (!(d[7])) + \
@@ -226,10 +241,92 @@ static inline bool hasLeading(uchar const *d)
 }
 #endif
 
+// whether the address starts with at least LEADING_NIBBLES zero nibbles
+static inline bool hasLeadingNibbles(uchar const *d)
+{
+#pragma unroll
+  for (uint i = 0; i < LEADING_NIBBLES; ++i) {
+    uchar nibble = (i & 1) ? (d[i >> 1] & 0x0fu) : (d[i >> 1] >> 4);
+    if (nibble) return false;
+  }
+  return true;
+}
+
+// whether the address has at least REPEAT_RUN consecutive identical nibbles
+static inline bool hasRepeatRun(uchar const *d)
+{
+  uint run = 1;
+  uchar previous = d[0] >> 4;
+#pragma unroll
+  for (uint i = 1; i < 40; ++i) {
+    uchar nibble = (i & 1) ? (d[i >> 1] & 0x0fu) : (d[i >> 1] >> 4);
+    run = (nibble == previous) ? run + 1 : 1;
+    if (run >= REPEAT_RUN) return true;
+    previous = nibble;
+  }
+  return REPEAT_RUN <= 1;
+}
+
+#if NUM_PREFIXES
+// whether the address starts with any of the prefixes, which are checked one
+// after the other from constant memory rather than unrolled so that long
+// lists don't blow up the kernel
+static inline bool hasPrefix(uchar const *d)
+{
+  for (uint p = 0; p < NUM_PREFIXES; ++p) {
+    bool match = true;
+#pragma unroll
+    for (uint i = 0; i < PREFIX_BYTES; ++i)
+      match &= (d[i] & prefixMasks[p][i]) == prefixTargets[p][i];
+    if (match) return true;
+  }
+  return false;
+}
+#endif
+
+#if PREFIX_BLOOM
+// whether the nibbles long key is in the Bloom filter of the prefixes
+static inline bool inPrefixBloom(uint key, uint nibbles, __global uint const *bloom)
+{
+  key ^= nibbles * 0xc2b2ae35u;
+  uint h1 = key * 0x9e3779b1u;
+  uint h2 = ((key ^ (key >> 15)) * 0x85ebca6bu) | 1u;
+#pragma unroll
+  for (uint i = 0; i < BLOOM_HASHES; ++i) {
+    uint x = h1 + i * h2;
+    x ^= x >> 16;
+    x *= 0x7feb352du;
+    x ^= x >> 15;
+    x &= BLOOM_MASK;
+    if (!(bloom[x >> 5] & (1u << (x & 31)))) return false;
+  }
+  return true;
+}
+
+// whether the address's first nibbles are in the Bloom filter of the
+// prefixes for any of the prefix lengths, which is also true for some
+// addresses starting with none of them, so the host checks the full prefixes
+// of every hit
+static inline bool hasPrefixBloom(uchar const *d, __global uint const *bloom)
+{
+  uint key = 0;
+  uint nibbles = 0;
+  for (uint k = 0; k < NUM_BLOOM_KEYS; ++k) {
+    // the lengths ascend, so the key only grows by the missing nibbles
+    for (; nibbles < bloomKeyNibbles[k]; ++nibbles)
+      key = (key << 4) | ((nibbles & 1) ? (d[nibbles >> 1] & 0x0fu) : (d[nibbles >> 1] >> 4));
+    if (inPrefixBloom(key, nibbles, bloom)) return true;
+  }
+  return false;
+}
+#endif
+
 __kernel void hashMessage(
   __constant uchar const *d_message,
   __constant uint const *d_nonce,
-  __global volatile ulong *restrict solutions
+  __global volatile uint *restrict solutionCount,
+  __global volatile ulong *restrict solutions,
+  __global uint const *restrict prefixBloom
 ) {
 
   ulong spongeBuffer[25];
@@ -239,151 +336,190 @@ __kernel void hashMessage(
 
   nonce_t nonce;
 
-  // write the control character
-  sponge[0] = 0xffu;
-
-  sponge[1] = S_1;
-  sponge[2] = S_2;
-  sponge[3] = S_3;
-  sponge[4] = S_4;
-  sponge[5] = S_5;
-  sponge[6] = S_6;
-  sponge[7] = S_7;
-  sponge[8] = S_8;
-  sponge[9] = S_9;
-  sponge[10] = S_10;
-  sponge[11] = S_11;
-  sponge[12] = S_12;
-  sponge[13] = S_13;
-  sponge[14] = S_14;
-  sponge[15] = S_15;
-  sponge[16] = S_16;
-  sponge[17] = S_17;
-  sponge[18] = S_18;
-  sponge[19] = S_19;
-  sponge[20] = S_20;
-  sponge[21] = S_21;
-  sponge[22] = S_22;
-  sponge[23] = S_23;
-  sponge[24] = S_24;
-  sponge[25] = S_25;
-  sponge[26] = S_26;
-  sponge[27] = S_27;
-  sponge[28] = S_28;
-  sponge[29] = S_29;
-  sponge[30] = S_30;
-  sponge[31] = S_31;
-  sponge[32] = S_32;
-  sponge[33] = S_33;
-  sponge[34] = S_34;
-  sponge[35] = S_35;
-  sponge[36] = S_36;
-  sponge[37] = S_37;
-  sponge[38] = S_38;
-  sponge[39] = S_39;
-  sponge[40] = S_40;
-
-  sponge[41] = d_message[0];
-  sponge[42] = d_message[1];
-  sponge[43] = d_message[2];
-  sponge[44] = d_message[3];
-
   // populate the nonce
   nonce.uint32_t[0] = get_global_id(0);
   nonce.uint32_t[1] = d_nonce[0];
 
-  // populate the body of the message with the nonce
-  sponge[45] = nonce.uint8_t[0];
-  sponge[46] = nonce.uint8_t[1];
-  sponge[47] = nonce.uint8_t[2];
-  sponge[48] = nonce.uint8_t[3];
-  sponge[49] = nonce.uint8_t[4];
-  sponge[50] = nonce.uint8_t[5];
-  sponge[51] = nonce.uint8_t[6];
-  sponge[52] = nonce.uint8_t[7];
-
-  sponge[53] = S_53;
-  sponge[54] = S_54;
-  sponge[55] = S_55;
-  sponge[56] = S_56;
-  sponge[57] = S_57;
-  sponge[58] = S_58;
-  sponge[59] = S_59;
-  sponge[60] = S_60;
-  sponge[61] = S_61;
-  sponge[62] = S_62;
-  sponge[63] = S_63;
-  sponge[64] = S_64;
-  sponge[65] = S_65;
-  sponge[66] = S_66;
-  sponge[67] = S_67;
-  sponge[68] = S_68;
-  sponge[69] = S_69;
-  sponge[70] = S_70;
-  sponge[71] = S_71;
-  sponge[72] = S_72;
-  sponge[73] = S_73;
-  sponge[74] = S_74;
-  sponge[75] = S_75;
-  sponge[76] = S_76;
-  sponge[77] = S_77;
-  sponge[78] = S_78;
-  sponge[79] = S_79;
-  sponge[80] = S_80;
-  sponge[81] = S_81;
-  sponge[82] = S_82;
-  sponge[83] = S_83;
-  sponge[84] = S_84;
-
-  // begin padding based on message length
-  sponge[85] = 0x01u;
-
-  // fill padding
-  #pragma unroll
-  for (int i = 86; i < 135; ++i)
-    sponge[i] = 0;
-
-  // end padding
-  sponge[135] = 0x80u;
-
-  // fill remaining sponge state with zeroes
-  #pragma unroll
-  for (int i = 136; i < 200; ++i)
-    sponge[i] = 0;
-
-  // Apply keccakf
-  keccakf(spongeBuffer);
-
-  uchar deployProxy[20];
-
-  #pragma unroll
-  for (int i = 0; i < 20; ++i)
-    deployProxy[i] = digest[i];
-
-
-  for (uchar create1Nonce = 1; create1Nonce <= MAX_NONCE; ++create1Nonce) {
-    sponge[0] = 0xd6u;
-    sponge[1] = 0x94u;
+  // try every init code hash, reporting which one matched
+  for (uint hashIndex = 0; hashIndex < NUM_HASHES; ++hashIndex) {
+    // write the control character
+    sponge[0] = 0xffu;
+
+    sponge[1] = S_1;
+    sponge[2] = S_2;
+    sponge[3] = S_3;
+    sponge[4] = S_4;
+    sponge[5] = S_5;
+    sponge[6] = S_6;
+    sponge[7] = S_7;
+    sponge[8] = S_8;
+    sponge[9] = S_9;
+    sponge[10] = S_10;
+    sponge[11] = S_11;
+    sponge[12] = S_12;
+    sponge[13] = S_13;
+    sponge[14] = S_14;
+    sponge[15] = S_15;
+    sponge[16] = S_16;
+    sponge[17] = S_17;
+    sponge[18] = S_18;
+    sponge[19] = S_19;
+    sponge[20] = S_20;
+    sponge[21] = S_21;
+    sponge[22] = S_22;
+    sponge[23] = S_23;
+    sponge[24] = S_24;
+    sponge[25] = S_25;
+    sponge[26] = S_26;
+    sponge[27] = S_27;
+    sponge[28] = S_28;
+    sponge[29] = S_29;
+    sponge[30] = S_30;
+    sponge[31] = S_31;
+    sponge[32] = S_32;
+    sponge[33] = S_33;
+    sponge[34] = S_34;
+    sponge[35] = S_35;
+    sponge[36] = S_36;
+    sponge[37] = S_37;
+    sponge[38] = S_38;
+    sponge[39] = S_39;
+    sponge[40] = S_40;
+
+    #pragma unroll
+    for (int i = 0; i < SALT_BYTES; ++i)
+      sponge[41 + i] = d_message[i];
+
+    // populate the rest of the salt with the nonce, the work item's index
+    // followed by as many bytes of the outer nonce as still fit
+    #pragma unroll
+    for (int i = 0; i < 12 - SALT_BYTES; ++i)
+      sponge[41 + SALT_BYTES + i] = nonce.uint8_t[i];
+
+#if CREATE3_VARIANT
+    // the factory hashes the owner together with the salt it's called with
+    // and creates the deploy proxy with the result, packed for solmate and
+    // abi encoded behind a zero byte for createx
+#if CREATE3_VARIANT == 2
+#define SALT_PREIMAGE_OFFSET 12
+    sponge[41] = 0;
+#else
+#define SALT_PREIMAGE_OFFSET 0
+#endif
+    uchar header[21];
+    uchar salt[32];
+
+    #pragma unroll
+    for (int i = 0; i < 21; ++i)
+      header[i] = sponge[i];
+
+    #pragma unroll
+    for (int i = 0; i < 32; ++i)
+      salt[i] = sponge[i + 21];
+
+    #pragma unroll
+    for (int i = 0; i < SALT_PREIMAGE_OFFSET; ++i)
+      sponge[i] = 0;
+
+    // the owner is the start of the salt, as there's no salt prefix
     #pragma unroll
     for (int i = 0; i < 20; ++i)
-      sponge[i + 2] = deployProxy[i];
-    sponge[22] = create1Nonce;
-    sponge[23] = 0x01u;
+      sponge[i + SALT_PREIMAGE_OFFSET] = salt[i];
+
+    #pragma unroll
+    for (int i = 0; i < 32; ++i)
+      sponge[i + SALT_PREIMAGE_OFFSET + 20] = salt[i];
+
+    sponge[SALT_PREIMAGE_OFFSET + 52] = 0x01u;
+    #pragma unroll
+    for (int i = SALT_PREIMAGE_OFFSET + 53; i < 135; ++i)
+      sponge[i] = 0;
+    sponge[135] = 0x80u;
+    #pragma unroll
+    for (int i = 136; i < 200; ++i)
+      sponge[i] = 0;
+
+    keccakfFull(spongeBuffer);
+
+    #pragma unroll
+    for (int i = 0; i < 32; ++i)
+      salt[i] = sponge[i];
+
+    #pragma unroll
+    for (int i = 0; i < 21; ++i)
+      sponge[i] = header[i];
+
+    #pragma unroll
+    for (int i = 0; i < 32; ++i)
+      sponge[i + 21] = salt[i];
+#endif
+
     #pragma unroll
-    for (int i = 24; i < 135; ++i)
+    for (int i = 0; i < 32; ++i)
+      sponge[i + 53] = initCodeHashes[hashIndex][i];
+
+    // begin padding based on message length
+    sponge[85] = 0x01u;
+
+    // fill padding
+    #pragma unroll
+    for (int i = 86; i < 135; ++i)
       sponge[i] = 0;
+
+    // end padding
     sponge[135] = 0x80u;
+
+    // fill remaining sponge state with zeroes
     #pragma unroll
     for (int i = 136; i < 200; ++i)
       sponge[i] = 0;
 
+    // Apply keccakf
     keccakf(spongeBuffer);
 
-    // determine if the address meets the constraints
-    if (SUCCESS_CONDITION()) {
-      solutions[0] = nonce.uint64_t;
-      solutions[1] = (ulong) create1Nonce;
-      break;
+    uchar deployProxy[20];
+
+    #pragma unroll
+    for (int i = 0; i < 20; ++i)
+      deployProxy[i] = digest[i];
+
+
+    for (uchar nonceIndex = 0; nonceIndex < NUM_NONCES; ++nonceIndex) {
+      uchar create1Nonce = create1Nonces[nonceIndex];
+#if !CREATE2_MODE
+      sponge[0] = 0xd6u;
+      sponge[1] = 0x94u;
+      #pragma unroll
+      for (int i = 0; i < 20; ++i)
+        sponge[i + 2] = deployProxy[i];
+      sponge[22] = create1Nonce;
+      sponge[23] = 0x01u;
+      #pragma unroll
+      for (int i = 24; i < 135; ++i)
+        sponge[i] = 0;
+      sponge[135] = 0x80u;
+      #pragma unroll
+      for (int i = 136; i < 200; ++i)
+        sponge[i] = 0;
+
+      keccakf(spongeBuffer);
+#endif
+      // in create2 mode the digest still holds the deploy proxy address,
+      // NUM_NONCES is 1 so it's only checked once
+
+      // determine if the address meets the constraints
+      if (SUCCESS_CONDITION()) {
+        // claim a slot in the solutions list, hits beyond its capacity are
+        // still counted but dropped
+        uint slot = atomic_inc(solutionCount);
+        if (slot < MAX_SOLUTIONS) {
+          solutions[slot * 4] = nonce.uint64_t;
+          solutions[slot * 4 + 1] = (ulong) create1Nonce;
+          solutions[slot * 4 + 2] = (ulong) hashIndex;
+          solutions[slot * 4 + 3] = MATCHED_FLAGS();
+        }
+        return;
+      }
     }
   }
 }
\ No newline at end of file
@@ -1,38 +1,422 @@
 #![warn(unused_crate_dependencies, unreachable_pub)]
 #![deny(unused_must_use, rust_2018_idioms)]
 
-use alloy_primitives::{hex, Address, FixedBytes};
-use byteorder::{BigEndian, ByteOrder, LittleEndian};
+// only used by the binary
+use clap_num as _;
+use ctrlc as _;
+use tracing_subscriber as _;
+
+use alloy_primitives::{b256, hex, keccak256, Address, Bytes, FixedBytes};
 use console::Term;
-use fs4::FileExt;
+use ocl::core::{DeviceInfo, DeviceInfoResult, ProgramBuildInfo, ProgramBuildInfoResult};
 use ocl::{Buffer, Context, Device, MemFlags, Platform, ProQue, Program, Queue};
-use rand::{thread_rng, Rng};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use regex::Regex;
+use rustc_hash::{FxHashMap, FxHashSet};
 use separator::Separatable;
+use serde::{Deserialize, Serialize};
 use std::fmt::Write as _;
-use std::fs::{File, OpenOptions};
-use std::io::prelude::*;
-use std::time::{SystemTime, UNIX_EPOCH};
-use terminal_size::{terminal_size, Height};
+use std::net::{SocketAddr, TcpListener};
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{JoinHandle, Scope, ScopedJoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{info, info_span, warn};
+
+mod config;
+pub use config::ConfigBuilder;
+
+mod cpu;
+pub use cpu::{cpu, cpu_with_callback};
+
+mod metrics;
+use metrics::Metrics;
+
+mod notify;
+use notify::Notifier;
+pub use notify::NotifyOverflow;
+
+mod stream;
+use stream::SolutionStream;
+
+mod match_byte;
+pub use match_byte::MatchByte;
+
+mod prefix;
+pub use prefix::AddressPrefix;
+use prefix::PrefixBloom;
+
+mod address_range;
+pub use address_range::AddressRange;
+
+mod output;
+use output::OutputFile;
+pub use output::{OutputFormat, OutputTemplate};
 
 mod reward;
-pub use reward::Reward;
+pub use reward::{Reward, Tier};
+
+mod state;
+pub use state::SearchState;
+
+mod summary;
+pub use summary::default_summary_file;
+use summary::{RunSummary, ZeroHistogram};
+
+mod verify;
+pub use verify::{verify, Verification};
+
+mod kernel_check;
+pub use kernel_check::check_kernels;
 
 static KERNEL_SRC: &str = include_str!("./kernels/keccak256.cl");
 
+/// How many solutions a single work batch can report, further hits found in
+/// the same batch are dropped.
+const MAX_SOLUTIONS: usize = 64;
+
+/// The names of the kernel's predicates, in the order of the bits it sets in
+/// the flags word of a hit for those that held. `prefix` only tells that some
+/// prefix matched, which ones is checked on the host.
+const CONDITION_NAMES: [&str; 6] = [
+    "leading_zeros",
+    "total_zeros",
+    "leading_nibbles",
+    "repeat_run",
+    "prefix",
+    "match_bytes",
+];
+
+/// How many batches of candidates can wait for verification before the
+/// devices reporting more of them are held up.
+const CANDIDATE_QUEUE: usize = 16;
+
+/// The most threads verifying candidates on the host.
+const MAX_VERIFIERS: usize = 4;
+
+/// How many lines of the output file the dashboard shows before this run
+/// found anything.
+const HISTORY_LINES: usize = 64;
+
+/// The smallest work size a search can be run with.
+const MIN_WORK_SIZE: u32 = 0x1540000;
+
+/// The smallest work size with `allow_small_work_size`, which still leaves
+/// the self-test distinct work items to check.
+const MIN_SMALL_WORK_SIZE: u32 = 0x100;
+
+/// How many batches per device are left out of rates, as the first ones
+/// include compiling the kernel and warming up the driver.
+const WARMUP_BATCHES: u64 = 2;
+
+/// The largest work size `autotune` measures.
+const MAX_AUTOTUNE_WORK_SIZE: u32 = 0x40000000;
+
+/// How long `autotune` measures each work size for.
+const AUTOTUNE_DURATION: Duration = Duration::from_secs(2);
+
+/// The parameters of a search, see [`Config::builder`] for building one with
+/// the command line's defaults.
 pub struct Config {
     pub factory: Address,
     pub owner: Address,
-    pub init_code_hash: FixedBytes<32>,
+    /// The deploy proxy initcode hashes to search, all of them are checked for
+    /// every salt.
+    pub init_code_hashes: Vec<FixedBytes<32>>,
     pub work_size: u32,
-    pub gpu_device: u8,
+    pub gpu_devices: Vec<u8>,
     pub max_create3_nonce: u8,
+    /// The deploy proxy's create1 nonces to search instead of
+    /// `1..=max_create3_nonce`, each between 1 and 127.
+    pub proxy_nonces: Option<Vec<u8>>,
     pub leading_zeroes_threshold: Option<u8>,
     pub total_zeroes_threshold: Option<u8>,
+    /// Minimum amount of leading zero nibbles, counted independently of the
+    /// leading zero bytes.
+    pub leading_zero_nibbles_threshold: Option<u8>,
+    /// Minimum length of a run of identical nibbles anywhere in the address.
+    pub repeat_run: Option<u8>,
+    /// Prefixes of which the address has to start with any one. They're
+    /// checked on the GPU one after the other, so a long list slows every
+    /// hash down somewhat. Lists too long for the kernel's constant memory
+    /// are checked against a Bloom filter of their first nibbles instead, up
+    /// to 8 of them keyed by each prefix's own length, and the host checks
+    /// the full prefixes.
+    pub prefixes: Vec<AddressPrefix>,
+    /// Bytes the address has to have at their positions, all of them. The
+    /// rest of the address is left free.
+    pub match_bytes: Vec<MatchByte>,
+    /// Pattern the checksummed address has to match in addition to the
+    /// thresholds. It can't be evaluated on the GPU, so it only filters the
+    /// solutions the thresholds let through on the host. Without any
+    /// threshold every address is a candidate and the GPU miner can only
+    /// report `MAX_SOLUTIONS` of them per work batch, slowing the search down
+    /// by orders of magnitude - pair it with a threshold that's as strict as
+    /// the pattern allows.
+    pub checksum_regex: Option<Regex>,
+    /// Minimum amount of uppercase letters in the checksummed address, checked
+    /// on the host like `checksum_regex`.
+    pub min_uppercase: Option<u8>,
+    /// Range the address has to lie in as a 160-bit integer, checked on the
+    /// host like `checksum_regex`. Without a threshold, the leading zero
+    /// nibbles the range implies are used as one.
+    pub address_range: Option<AddressRange>,
     pub output_file: String,
+    pub output_format: OutputFormat,
+    /// Line to write solutions as in the `text` format instead of the default
+    /// one.
+    pub output_template: Option<OutputTemplate>,
+    /// Tag every written solution with an id of this tag, a random id of the
+    /// run and the solution's sequence number in it, `TAG:RUN:SEQ`, to tell
+    /// apart the solutions of many miners in merged output files.
+    pub tag: Option<String>,
+    /// Sync the output file to disk after every solution, so a power loss
+    /// can't take found salts with it. Every write then waits on the disk,
+    /// which slows down searches for thresholds that are met often.
+    pub durable: bool,
+    /// When set to `true` the search stops after the current work batch.
+    pub stop_flag: Option<Arc<AtomicBool>>,
+    /// File to periodically save the search state to and resume it from.
+    pub state_file: Option<String>,
+    /// TOML file overriding entries of the built-in reward table.
+    pub reward_file: Option<String>,
+    /// Print a status line per interval instead of redrawing the dashboard.
+    pub quiet: bool,
+    /// Print the platform, the devices, the work sizes, the kernel build log
+    /// and the success condition before mining, for bug reports. Implies
+    /// `quiet` so they stay on screen.
+    pub verbose: bool,
+    /// The byte order the dashboard shows each device's nonce in.
+    pub nonce_endian: NonceEndian,
+    /// Stop the search once this many solutions were written.
+    pub max_results: Option<usize>,
+    /// Drop solutions whose reward scores lower than this, see
+    /// [`Reward::score`].
+    pub min_reward_score: Option<f64>,
+    /// End the search once a solution is worth at least this reward, a
+    /// number or a reward of the table, see [`Reward::tier`].
+    pub stop_at_reward: Option<String>,
+    /// Stop the search once it ran for this long, regardless of how many
+    /// solutions were found.
+    pub timeout: Option<Duration>,
+    /// File to append a line of JSON summarizing the run to once the search
+    /// stops.
+    pub summary_file: Option<String>,
+    /// JSON file to keep the rate, solutions found, cumulative nonce and
+    /// uptime of the running search in, for dashboards that poll a file.
+    pub stats_file: Option<String>,
+    /// How often the stats file is rewritten, at most once per dashboard
+    /// update, i.e. about every second.
+    pub stats_interval: Duration,
+    /// Percentage of the previous work duration to sleep for before reading
+    /// the results, 0 busy-waits on the device for the highest hash rate.
+    pub throttle: u8,
+    /// Seeds the generation of salts and nonces to make a search reproducible.
+    pub seed: Option<u64>,
+    /// How the factory derives the final address from the salt.
+    pub mode: Mode,
+    /// How a CREATE3 factory turns the salt it's called with into the salt
+    /// its deploy proxy is created with.
+    pub create3_variant: Create3Variant,
+    /// Up to 20 bytes used as the start of every salt in place of the owner,
+    /// zero padded on the right. Factories that bind salts to the caller will
+    /// reject salts that don't start with it, so only set this for factories
+    /// that accept arbitrary salts.
+    pub salt_prefix: Option<Bytes>,
+    /// Draw the first 20 bytes of the salt at random at the start of every
+    /// search instead of using the owner, for factories that hash the whole
+    /// salt without binding it to the caller. Anyone can deploy with such a
+    /// salt, so a deployment with it can be front-run.
+    pub free_salt: bool,
+    /// How many bytes of the salt following the prefix are drawn at random,
+    /// between 4 and 8. The rest of the salt is enumerated, so more random
+    /// bytes make collisions between many miners less likely but exhaust each
+    /// random salt sooner.
+    pub salt_bytes: u8,
+    /// Whether every salt is drawn at random or the salts are searched in
+    /// order, starting at `salt_start`.
+    pub salt_mode: SaltMode,
+    /// The first salt of a sequential search, read as a big-endian number of
+    /// `salt_bytes` bytes. Every device starts at its own offset from it and
+    /// steps by the number of devices, so none of them search the same salt.
+    pub salt_start: u64,
+    /// The salt and nonce the search starts at instead of a random or
+    /// `salt_start` one, to split a search between machines by hand. Only
+    /// for a single device, which moves on from it like from any other start.
+    pub start: Option<SearchState>,
+    /// How many batches of nonces a random salt is searched for at most
+    /// before moving on to a fresh salt, rather than until a hit or until its
+    /// nonces run out. Smaller strides search more distinct salts at the same
+    /// work size.
+    pub nonce_stride: Option<u32>,
+    /// Address to serve Prometheus metrics of the search on.
+    pub metrics_addr: Option<SocketAddr>,
+    /// Address to serve a WebSocket on, which sends every solution written
+    /// to the output file to its clients as a JSON text message.
+    pub ws_addr: Option<SocketAddr>,
+    /// Require addresses to meet every set threshold rather than any of them.
+    pub require_all: bool,
+    /// Round `work_size` down to fit the devices instead of failing.
+    pub auto_clamp: bool,
+    /// Accept work sizes below the usual minimum, for quick iterations while
+    /// developing on a weak device. Searches with them are inefficient.
+    pub allow_small_work_size: bool,
+    /// Measure the hash rate at a range of work sizes before the search and
+    /// search with the fastest one rather than `work_size`.
+    pub autotune: bool,
+    /// Run at most this many work groups at a time, so the kernel occupies
+    /// at most this many of a device's compute units and a GPU shared with a
+    /// display or other work keeps the rest free. Clamped to the compute
+    /// units each device has; every batch still covers `work_size` salts, in
+    /// several launches of this many work groups.
+    pub max_compute_units: Option<u32>,
+    /// Check every device's keccak implementation against the host's before
+    /// mining on it.
+    pub self_test: bool,
+    /// Ring the bell for solutions with at least this many zero bytes.
+    pub notify_threshold: Option<u8>,
+    /// Command to additionally run for such solutions, with the address and
+    /// reward as arguments.
+    pub notify_cmd: Option<String>,
+    /// How many notification commands run at once, at least 1.
+    pub notify_concurrency: u8,
+    /// Whether notification commands are dropped or the search waits while
+    /// `notify_concurrency` of them are running and as many are queued.
+    pub notify_overflow: NotifyOverflow,
+}
+
+/// The deployment scheme used by the factory.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// CREATE2 deploys a proxy which deploys the contract through CREATE, with
+    /// create3 nonces up to `max_create3_nonce` being searched.
+    #[default]
+    Create3,
+    /// The contract is deployed directly through CREATE2.
+    Create2,
+}
+
+/// The byte order of the nonce in the dashboard's search space lines. Each
+/// line shows the random salt bytes, `xxxxxxxx` for the four bytes of the
+/// work item index every work item fills in itself, and then the bytes of
+/// the device's current nonce that still fit into the salt.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum NonceEndian {
+    /// Little-endian, the nonce's bytes as they appear in the salt passed to
+    /// the factory.
+    #[default]
+    Le,
+    /// Big-endian, the nonce's bytes in the order the number reads.
+    Be,
+}
+
+/// How miners move on to the next salt.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SaltMode {
+    /// Every salt is drawn at random and searched from a random nonce, moving
+    /// on to the next one after each hit.
+    #[default]
+    Random,
+    /// Salts are counted up from `salt_start` and searched from the first
+    /// nonce to the last, so the searched space is contiguous and the same
+    /// on every run. Searches with disjoint ranges of salts never overlap.
+    Sequential,
+}
+
+/// The initcode hash of the deploy proxy shared by most CREATE3 libraries,
+/// `0x67363d3d37363d34f03d5260086018f3`.
+const CREATE3_PROXY_HASH: FixedBytes<32> =
+    b256!("21c35dbe1b344a2488cf3321d6ce542f8e9f305544ff09e4993a62319a497c1f");
+
+/// The schemes CREATE3 factories use to derive the deploy proxy's salt from
+/// the salt they're called with. Searching with the wrong one finds salts
+/// that deploy somewhere else entirely.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Create3Variant {
+    /// The salt is used as is, the owner is only bound by being its first 20
+    /// bytes.
+    #[default]
+    Default,
+    /// Solmate's `CREATE3Factory`, which hashes the caller together with the
+    /// salt: `keccak256(abi.encodePacked(owner, salt))`. Only create3 nonce 0
+    /// is deployed to.
+    Solmate,
+    /// CreateX's permissioned, cross-chain salts: the owner followed by a zero
+    /// byte, hashed as `keccak256(abi.encode(owner, salt))`. The zero byte
+    /// takes the place of the first random byte of the salt. Only create3
+    /// nonce 0 is deployed to.
+    #[value(name = "createx")]
+    CreateX,
+}
+
+impl Create3Variant {
+    /// The initcode hash of the deploy proxy the factory uses, if it's fixed.
+    pub fn proxy_init_code_hash(self) -> Option<FixedBytes<32>> {
+        match self {
+            Create3Variant::Default => None,
+            Create3Variant::Solmate | Create3Variant::CreateX => Some(CREATE3_PROXY_HASH),
+        }
+    }
+}
+
+/// A salt found by the miner along with the address it deploys to.
+#[derive(Clone, Debug)]
+pub struct Solution {
+    /// The full 32-byte salt to pass to the CREATE3 factory.
+    pub create2_salt: [u8; 32],
+    /// The create3 nonce, i.e. the deploy proxy's create1 nonce minus one.
+    /// Always 0 in create2 mode.
+    pub nonce: u64,
+    /// The address the contract will be deployed to.
+    pub address: Address,
+    /// The address of the deploy proxy the factory creates through CREATE2,
+    /// which then deploys the contract. The same as `address` in create2
+    /// mode.
+    pub proxy_address: Address,
+    /// The number of zero bytes in `address`.
+    pub total_zeros: usize,
+    /// The number of leading zero bytes in `address`.
+    pub leading_zeros: usize,
+    /// The number of leading zero nibbles in `address`, which is also the
+    /// number of leading `0`s of its checksummed form as checksumming leaves
+    /// digits alone.
+    pub leading_zero_nibbles: usize,
+    /// The number of uppercase letters in the checksummed `address`.
+    pub uppercase_letters: usize,
+    /// The approximate rarity of `address`.
+    pub reward: String,
+    /// `reward` as a number, see [`Reward::score`].
+    pub reward_score: f64,
+    /// The initcode hash of the deploy proxy the salt was found for.
+    pub init_code_hash: FixedBytes<32>,
+    /// The conditions the kernel reported `address` meets, see
+    /// [`matched_conditions`].
+    pub matched: Vec<String>,
+}
+
+impl std::fmt::Display for Solution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "0x{} ({}) => {} => {} (proxy {})",
+            hex::encode(self.create2_salt),
+            self.nonce,
+            self.address,
+            self.reward,
+            self.proxy_address
+        )
+    }
 }
 
 /// Given a Config object with a factory address, a caller address, a keccak-256
-/// hash of the contract initialization code, and a device ID, search for salts
+/// hash of the contract initialization code, and device IDs, search for salts
 /// using OpenCL that will enable the factory contract to deploy a contract to a
 /// gas-efficient address via CREATE3. This method also takes threshold values
 /// for both leading zero bytes and total zero bytes - any address that does not
@@ -41,145 +425,260 @@ pub struct Config {
 ///
 /// The 32-byte salt is constructed as follows:
 ///   - the 20-byte calling address (to prevent frontrunning)
-///   - a random 4-byte segment (to prevent collisions with other runs)
+///   - a random `salt_bytes` segment (to prevent collisions with other runs),
+///     4 bytes by default
 ///   - a 4-byte segment unique to each work group running in parallel
-///   - a 4-byte nonce segment (incrementally stepped through during the run)
+///   - the remaining bytes as a nonce segment (incrementally stepped through
+///     during the run)
 ///
 /// When a salt that will result in the creation of a gas-efficient contract
 /// address is found, it will be appended to `efficient_addresses.txt` along
 /// with the resultant address and the "value" (i.e. approximate rarity) of the
 /// resultant address.
 ///
+/// The search runs until it is stopped, until `max_results` solutions were
+/// written or until `timeout` passed, whichever comes first.
+///
 /// This method is still highly experimental and could almost certainly use
 /// further optimization - contributions are more than welcome!
 pub fn gpu(config: Config) -> ocl::Result<()> {
-    println!(
-        "Setting up experimental OpenCL miner using device {}...",
-        config.gpu_device
-    );
-
     // (create if necessary) and open a file where found salts will be written
-    let file = output_file(&config.output_file);
+    let file = OutputFile::open(&config)?;
 
-    // create object for computing rewards (relative rarity) for a given address
-    let rewards = Reward::new();
+    let notifier = Notifier::new(&config);
+    let stream = SolutionStream::new(&config)?;
+    let target = RewardTarget::new(&config)?;
+    let max_results = config.max_results;
+    let mut written = 0;
+    let result = gpu_with_callback(config, |solution| {
+        file.write(solution);
+        notifier.notify(solution);
+        stream.send(solution);
+        written += 1;
+        if target
+            .as_ref()
+            .map_or(false, |target| target.reached(solution))
+        {
+            return ControlFlow::Break(());
+        }
+        enough_results(max_results, written)
+    });
+
+    file.close();
+
+    result
+}
+
+/// Runs the same search as [`gpu`], but rather than writing solutions to the
+/// output file, invokes `on_solution` for every solution that is found. The
+/// search stops once the callback returns [`ControlFlow::Break`] or once the
+/// config's `stop_flag` is raised.
+///
+/// Salts already in the configured output file or reported earlier in the run
+/// are skipped, so `on_solution` sees every salt at most once.
+///
+/// Every configured device is driven from its own thread, each searching its
+/// own random salts, while the calling thread renders the dashboard. The
+/// candidates the devices report are verified on a few more threads, so the
+/// devices don't wait on the host-side filters, and `on_solution` is called
+/// from those.
+pub fn gpu_with_callback<F>(mut config: Config, on_solution: F) -> ocl::Result<()>
+where
+    F: FnMut(&Solution) -> ControlFlow<()> + Send,
+{
+    // set up a controller for terminal output, falling back to plain status
+    // lines if it isn't a terminal or its size is unknown, as clearing the
+    // screen would only garble the output then, and if the diagnostics are
+    // printed, as clearing it would wipe them
+    let term = output::status_term(&config.output_file);
+    let quiet = config.quiet || config.verbose || !term.is_term() || term.size_checked().is_none();
 
-    // track how many addresses have been found and information about them
-    let mut found: u64 = 0;
-    let mut found_list: Vec<String> = vec![];
+    term.write_line(&format!(
+        "Setting up experimental OpenCL miner using device(s) {:?}...",
+        config.gpu_devices
+    ))?;
+    draw_free_salt(&mut config);
+    if let Some(warning) = describe_free_salt(&config) {
+        term.write_line(&warning)?;
+    }
+    if let Some(nonces) = describe_nonces(&config) {
+        term.write_line(&nonces)?;
+    }
 
-    // set up a controller for terminal output
-    let term = Term::stdout();
+    // create object for computing rewards (relative rarity) for a given address
+    let rewards = load_rewards(&config)?;
 
     // set up a platform to use
-    let platform = Platform::new(ocl::core::default_platform()?);
+    let platform = default_platform()?;
+    info!(
+        platform = %platform.name()?,
+        devices = ?config.gpu_devices,
+        "setting up OpenCL miner"
+    );
+    if config.autotune {
+        term.write_line("Autotuning work size...")?;
+        autotune(&mut config, &term)?;
+        term.write_line(&format!("Using work size {:#x}.", config.work_size))?;
+    }
+    fit_work_size(&mut config, platform)?;
 
-    // set up the device to use
-    let device = Device::by_idx_wrap(platform, config.gpu_device as usize)?;
+    if config.self_test {
+        for device in config.gpu_devices.clone() {
+            self_test(&mut config, platform, device)?;
+        }
+        term.write_line("Keccak self-test passed on every device.")?;
+    }
 
-    // set up the context to use
-    let context = Context::builder()
-        .platform(platform)
-        .devices(device)
-        .build()?;
+    // every device compiles the same kernel
+    let kernel_src = mk_kernel_src(&config);
 
-    // set up the program to use
-    let program = Program::builder()
-        .devices(device)
-        .src(mk_kernel_src(&config))
-        .build(&context)?;
+    if config.verbose {
+        print_diagnostics(&term, &config, platform, &kernel_src)?;
+    }
 
-    // set up the queue to use
-    let queue = Queue::new(&context, device, None)?;
+    // resume from a previously saved search state if there is one
+    let mut resumed = match &config.state_file {
+        Some(path) => SearchState::load(path)?,
+        None => FxHashMap::default(),
+    };
+
+    // every device draws its salts from its own random number generator
+    let mut rngs: Vec<StdRng> = config
+        .gpu_devices
+        .iter()
+        .map(|&device| search_rng(&config, device))
+        .collect();
+
+    // track the search state of each device
+    let progress: Vec<Mutex<SearchState>> = config
+        .gpu_devices
+        .iter()
+        .zip(&mut rngs)
+        .enumerate()
+        .map(|(slot, (device, rng))| {
+            let state = match resumed.remove(device) {
+                Some(state) => {
+                    let _ = term.write_line(&format!(
+                        "Resuming search on device {} from salt {} at nonce {}...",
+                        device, state.salt, state.nonce
+                    ));
+                    state
+                }
+                None => initial_state(&config, rng, slot),
+            };
+            Mutex::new(state)
+        })
+        .collect();
+    let resumed_nonce: u64 = progress
+        .iter()
+        .map(|state| state.lock().unwrap().cumulative_nonce)
+        .sum();
+
+    // skip the salts that were already written in a previous run, and show
+    // the last of them until new ones are found
+    let seen = output::read_salts(&config.output_file)?;
+    let history = output::tail_lines(&config.output_file, HISTORY_LINES)?;
+    let history_len = history.len();
+
+    let shared = Shared {
+        stop_flag: config.stop_flag.clone().unwrap_or_default(),
+        done: AtomicBool::new(false),
+        deadline: config.timeout.map(|timeout| Instant::now() + timeout),
+        found: Mutex::new(Found {
+            count: 0,
+            list: history,
+            seen,
+            duplicates: 0,
+            rejected: 0,
+            ended: false,
+            histogram: ZeroHistogram::default(),
+            colors: output::colors_enabled(&term),
+            on_solution,
+        }),
+    };
 
     let work_size = config.work_size;
-    // set up the "proqueue" (or amalgamation of various elements) to use
-    let ocl_pq = ProQue::new(context, queue, program, Some(work_size));
     let work_factor = (work_size as u128) / 1_000_000;
 
-    // create a random number generator
-    let mut rng = thread_rng();
-
     // determine the start time
     let start_time: f64 = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs_f64();
 
-    // set up variables for tracking performance
-    let mut rate: f64 = 0.0;
-    let mut cumulative_nonce: u64 = 0;
-
-    // the previous timestamp of printing to the terminal
-    let mut previous_time: f64 = 0.0;
-
-    // the last work duration in milliseconds
-    let mut work_duration_millis: u64 = 0;
+    // listen for metrics scrapers if requested
+    let metrics = Metrics::new();
+    let metrics_listener = match config.metrics_addr {
+        Some(addr) => Some(TcpListener::bind(addr)?),
+        None => None,
+    };
+    let hashes_per_cycle =
+        work_size as u128 * create1_nonces(&config) as u128 * config.init_code_hashes.len() as u128;
 
-    // begin searching for addresses
-    loop {
-        // construct the 4-byte message to hash, leaving last 8 of salt empty
-        let salt = FixedBytes::<4>::random();
+    let result = std::thread::scope(|scope| {
+        let workers = spawn_miners(
+            scope,
+            &config,
+            &rewards,
+            platform,
+            &kernel_src,
+            &progress,
+            rngs,
+            &shared,
+        );
 
-        // build a corresponding buffer for passing the message to the kernel
-        let message_buffer = Buffer::builder()
-            .queue(ocl_pq.queue().clone())
-            .flags(MemFlags::new().read_only())
-            .len(4)
-            .copy_host_slice(&salt[..])
-            .build()?;
+        // serve the metrics until the search stops
+        if let Some(listener) = &metrics_listener {
+            let (metrics, shared) = (&metrics, &shared);
+            scope.spawn(move || {
+                if let Err(err) = metrics.serve(listener, || shared.should_stop()) {
+                    warn!(%err, "metrics server stopped");
+                }
+            });
+        }
 
-        // reset nonce & create a buffer to view it in little-endian
-        // for more uniformly distributed nonces, we shall initialize it to a random value
-        let mut nonce: [u32; 1] = rng.gen();
-        let mut view_buf = [0; 8];
+        let dashboard = (|| -> ocl::Result<()> {
+            // the attempts made (in millions) as of the previous status update,
+            // used to compute the rate over the last interval
+            let mut previous_attempts: u128 = 0;
+            let mut previous_instant = Instant::now();
 
-        // build a corresponding buffer for passing the nonce to the kernel
-        let mut nonce_buffer = Buffer::builder()
-            .queue(ocl_pq.queue().clone())
-            .flags(MemFlags::new().read_only())
-            .len(1)
-            .copy_host_slice(&nonce)
-            .build()?;
+            // the attempts made and the time once every device warmed up,
+            // which the average rate is measured from
+            let mut warmed_up: Option<(u128, Instant)> = None;
+            let warmup = WARMUP_BATCHES * config.gpu_devices.len() as u64;
 
-        // establish a buffer for nonces that result in desired addresses
-        let mut solutions: Vec<u64> = vec![0; 2];
-        let solutions_buffer = Buffer::builder()
-            .queue(ocl_pq.queue().clone())
-            .flags(MemFlags::new().write_only())
-            .len(solutions.len())
-            .copy_host_slice(&solutions)
-            .build()?;
+            // the previous timestamp of printing to the terminal
+            let mut previous_time: f64 = 0.0;
 
-        // repeatedly enqueue kernel to search for new addresses
-        loop {
-            // build the kernel and define the type of each buffer
-            let kern = ocl_pq
-                .kernel_builder("hashMessage")
-                .arg_named("message", None::<&Buffer<u8>>)
-                .arg_named("nonce", None::<&Buffer<u32>>)
-                .arg_named("solutions", None::<&Buffer<u64>>)
-                .build()?;
+            // how many of the found solutions were printed in quiet mode, the
+            // ones of previous runs already are in the output file
+            let mut printed = history_len;
 
-            // set each buffer
-            kern.set_arg("message", Some(&message_buffer))?;
-            kern.set_arg("nonce", Some(&nonce_buffer))?;
-            kern.set_arg("solutions", &solutions_buffer)?;
+            // when the stats file was last written
+            let mut previous_stats: Option<Instant> = None;
 
-            // enqueue the kernel
-            unsafe { kern.enq()? };
+            while !workers.iter().all(|worker| worker.is_finished()) {
+                std::thread::sleep(Duration::from_millis(100));
 
-            // calculate the current time
-            let mut now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-            let current_time = now.as_secs() as f64;
+                // calculate the current time
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+                let current_time = now.as_secs() as f64;
 
-            // we don't want to print too fast
-            let print_output = current_time - previous_time > 0.99;
-            previous_time = current_time;
+                // we don't want to print too fast
+                if current_time - previous_time <= 0.99 {
+                    continue;
+                }
+                previous_time = current_time;
 
-            // clear the terminal screen
-            if print_output {
-                term.clear_screen()?;
+                let states: Vec<(u8, SearchState)> = config
+                    .gpu_devices
+                    .iter()
+                    .copied()
+                    .zip(progress.iter().map(|state| *state.lock().unwrap()))
+                    .collect();
+                let cumulative_nonce: u64 =
+                    states.iter().map(|(_, state)| state.cumulative_nonce).sum();
 
                 // get the total runtime and parse into hours : minutes : seconds
                 let total_runtime = current_time - start_time;
@@ -189,186 +688,2371 @@ pub fn gpu(config: Config) -> ocl::Result<()> {
                     - (total_runtime_hrs * 3600) as f64
                     - (total_runtime_mins * 60) as f64;
 
-                // determine the number of attempts being made per second
-                let work_rate: u128 =
-                    work_factor * cumulative_nonce as u128 * config.max_create3_nonce as u128;
-                if total_runtime > 0.0 {
-                    rate = 1.0 / total_runtime;
+                // determine the number of attempts being made per second, summed
+                // over all devices, both over the last interval and on average
+                let attempts: u128 = work_factor
+                    * (cumulative_nonce - resumed_nonce) as u128
+                    * create1_nonces(&config) as u128
+                    * config.init_code_hashes.len() as u128;
+                let now_instant = Instant::now();
+                let rates = match warmed_up {
+                    Some((warm_attempts, warm_instant)) => Some((
+                        attempt_rate(attempts - previous_attempts, now_instant - previous_instant),
+                        attempt_rate(attempts - warm_attempts, now_instant - warm_instant),
+                    )),
+                    None => {
+                        if cumulative_nonce - resumed_nonce >= warmup {
+                            warmed_up = Some((attempts, now_instant));
+                        }
+                        None
+                    }
+                };
+                previous_attempts = attempts;
+                previous_instant = now_instant;
+                let rate_status = match rates {
+                    Some((rate, average_rate)) => {
+                        format!("{rate:.2} million attempts per second (average {average_rate:.2})")
+                    }
+                    None => "warming up...".to_string(),
+                };
+
+                if quiet {
+                    // print the solutions found since the last status line
+                    let (found, duplicates, rejected, newly_found) = {
+                        let found = shared.found.lock().unwrap();
+                        let newly_found = found.list[printed..].to_vec();
+                        printed = found.list.len();
+                        (found.count, found.duplicates, found.rejected, newly_found)
+                    };
+                    for line in &newly_found {
+                        term.write_line(line)?;
+                    }
+
+                    term.write_line(&format!(
+                        "runtime: {}:{:02}:{:02}, rate: {}, found: {}, duplicates skipped: {}, \
+                         rejected by filters: {}",
+                        total_runtime_hrs,
+                        total_runtime_mins,
+                        total_runtime_secs as u64,
+                        rate_status,
+                        found,
+                        duplicates,
+                        rejected
+                    ))?;
+                } else {
+                    // clear the terminal screen
+                    term.clear_screen()?;
+
+                    // calculate the terminal height, defaulting to a height of ten rows
+                    let height = term.size_checked().map_or(10, |(rows, _)| rows);
+
+                    // display information about the total runtime and work size
+                    term.write_line(&format!(
+                        "total runtime: {}:{:02}:{:02} ({} cycles)\t\t\t\
+                         work size per cycle: {}",
+                        total_runtime_hrs,
+                        total_runtime_mins,
+                        total_runtime_secs,
+                        cumulative_nonce,
+                        work_size.separated_string(),
+                    ))?;
+
+                    // display recently found solutions based on terminal height
+                    let header = 4 + states.len();
+                    let rows = if (height as usize) < header + 1 {
+                        1
+                    } else {
+                        height as usize - header
+                    };
+                    let (found, duplicates, rejected, recently_found) = {
+                        let found = shared.found.lock().unwrap();
+                        let last_rows: Vec<String> =
+                            found.list.iter().cloned().rev().take(rows).collect();
+                        let ordered: Vec<String> = last_rows.iter().cloned().rev().collect();
+                        (
+                            found.count,
+                            found.duplicates,
+                            found.rejected,
+                            ordered.join("\n"),
+                        )
+                    };
+
+                    // display information about the attempt rate and found solutions
+                    term.write_line(&format!(
+                        "rate: {}\t\ttotal found this run: {} (duplicates skipped: {}, \
+                         rejected by filters: {})",
+                        rate_status, found, duplicates, rejected
+                    ))?;
+
+                    // display information about the current search criteria
+                    let eta = match rates {
+                        Some((_, average_rate)) => {
+                            format_eta(average_rate, success_probability(&config))
+                        }
+                        None => "warming up...".to_string(),
+                    };
+                    term.write_line(&format!(
+                        "threshold: {}\t\tETA: {}",
+                        describe_conditions(&config),
+                        eta
+                    ))?;
+                    let salt_bytes = config.salt_bytes as usize;
+                    for (device, state) in &states {
+                        // the nonce is placed into the salt little-endian
+                        let mut nonce = state.nonce.to_le_bytes()[..8 - salt_bytes].to_vec();
+                        if config.nonce_endian == NonceEndian::Be {
+                            nonce.reverse();
+                        }
+                        term.write_line(&format!(
+                            "current search space (device {}): {}xxxxxxxx{}",
+                            device,
+                            hex::encode(&state.salt[..salt_bytes]),
+                            hex::encode(nonce),
+                        ))?;
+                    }
+
+                    term.write_line(&recently_found)?;
+                }
+
+                metrics.update(
+                    ((cumulative_nonce - resumed_nonce) as u128 * hashes_per_cycle) as u64,
+                    shared.found.lock().unwrap().count,
+                    rates.map_or(0.0, |(rate, _)| rate * 1_000_000.0),
+                );
+                if let Some(path) = &config.stats_file {
+                    if previous_stats
+                        .map_or(true, |previous| previous.elapsed() >= config.stats_interval)
+                    {
+                        metrics.write_stats(path, cumulative_nonce)?;
+                        previous_stats = Some(Instant::now());
+                    }
                 }
 
-                // fill the buffer for viewing the properly-formatted nonce
-                LittleEndian::write_u64(&mut view_buf, (nonce[0] as u64) << 32);
-
-                // calculate the terminal height, defaulting to a height of ten rows
-                let height = terminal_size().map(|(_w, Height(h))| h).unwrap_or(10);
-
-                // display information about the total runtime and work size
-                term.write_line(&format!(
-                    "total runtime: {}:{:02}:{:02} ({} cycles)\t\t\t\
-                     work size per cycle: {}",
-                    total_runtime_hrs,
-                    total_runtime_mins,
-                    total_runtime_secs,
-                    cumulative_nonce,
-                    work_size.separated_string(),
-                ))?;
-
-                // display information about the attempt rate and found solutions
-                term.write_line(&format!(
-                    "rate: {:.2} million attempts per second\t\t\t\
-                     total found this run: {}",
-                    work_rate as f64 * rate,
-                    found
-                ))?;
-
-                // display information about the current search criteria
-                term.write_line(&format!(
-                    "current search space: {}xxxxxxxx{:08x}\t\t\
-                     threshold: {:?} leading or {:?} total zeroes",
-                    hex::encode(salt),
-                    BigEndian::read_u64(&view_buf),
-                    config.leading_zeroes_threshold,
-                    config.total_zeroes_threshold
-                ))?;
-
-                // display recently found solutions based on terminal height
-                let rows = if height < 5 { 1 } else { height as usize - 4 };
-                let last_rows: Vec<String> = found_list.iter().cloned().rev().take(rows).collect();
-                let ordered: Vec<String> = last_rows.iter().cloned().rev().collect();
-                let recently_found = &ordered.join("\n");
-                term.write_line(recently_found)?;
+                // persist the search state so it can be resumed after a restart
+                if let Some(path) = &config.state_file {
+                    SearchState::save(path, &states)?;
+                }
             }
 
-            // increment the cumulative nonce (does not reset after a match)
-            cumulative_nonce += 1;
+            Ok(())
+        })();
 
-            // record the start time of the work
-            let work_start_time_millis = now.as_secs() * 1000 + now.subsec_nanos() as u64 / 1000000;
+        // stop the devices in case the dashboard failed
+        shared.done.store(true, Ordering::Relaxed);
 
-            // sleep for 98% of the previous work duration to conserve CPU
-            if work_duration_millis != 0 {
-                std::thread::sleep(std::time::Duration::from_millis(
-                    work_duration_millis * 980 / 1000,
-                ));
+        dashboard.and(join_miners(workers))
+    });
+
+    let states: Vec<(u8, SearchState)> = config
+        .gpu_devices
+        .iter()
+        .copied()
+        .zip(
+            progress
+                .into_iter()
+                .map(|state| state.into_inner().unwrap()),
+        )
+        .collect();
+    let cumulative_nonce: u64 = states.iter().map(|(_, state)| state.cumulative_nonce).sum();
+
+    // save the final search state
+    if let Some(path) = &config.state_file {
+        SearchState::save(path, &states)?;
+    }
+
+    // print a final summary of the run
+    let Found {
+        count: found,
+        histogram,
+        ..
+    } = shared.found.into_inner().unwrap();
+    let total_runtime = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64()
+        - start_time;
+    let total_runtime_secs = total_runtime as u64;
+    term.write_line(&format!(
+        "Stopped after {}:{:02}:{:02} ({} cycles), total found this run: {}",
+        total_runtime_secs / 3600,
+        total_runtime_secs % 3600 / 60,
+        total_runtime_secs % 60,
+        cumulative_nonce,
+        found
+    ))?;
+    if !histogram.is_empty() {
+        term.write_line(&histogram.to_string())?;
+    }
+
+    if let Some(path) = &config.summary_file {
+        let hashes = ((cumulative_nonce - resumed_nonce) as u128 * hashes_per_cycle) as u64;
+        RunSummary::new(
+            &config,
+            start_time,
+            cumulative_nonce,
+            hashes,
+            found,
+            &histogram,
+        )
+        .write(path)?;
+    }
+
+    result
+}
+
+/// A search running on a thread of its own, see [`spawn_gpu`].
+pub struct GpuSearch {
+    /// The solutions found so far, in the order they were found.
+    pub solutions: Receiver<Solution>,
+    stop_flag: Arc<AtomicBool>,
+    thread: JoinHandle<ocl::Result<()>>,
+}
+
+impl GpuSearch {
+    /// Asks the search to stop, it ends after the current work batches.
+    pub fn stop(&self) {
+        self.stop_flag.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the search ended, either way.
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Waits for the search to end and returns how it did.
+    pub fn join(self) -> ocl::Result<()> {
+        self.thread.join().expect("Search thread panicked.")
+    }
+}
+
+/// Runs the same search as [`gpu_with_callback`] on a thread of its own and
+/// streams the solutions over a channel, so it can be driven from an event
+/// loop or an async runtime without blocking it: a runtime can await
+/// [`GpuSearch::join`] on a blocking task and poll or bridge `solutions`.
+///
+/// The search ends once the receiver is dropped, once [`GpuSearch::stop`] is
+/// called or the Config's `stop_flag` raised, or once it would have ended
+/// anyway. Nothing is written to the output file.
+pub fn spawn_gpu(mut config: Config) -> GpuSearch {
+    let stop_flag = config
+        .stop_flag
+        .get_or_insert_with(Default::default)
+        .clone();
+    let (sender, solutions) = mpsc::channel();
+    let thread = std::thread::spawn(move || {
+        gpu_with_callback(config, move |solution| {
+            match sender.send(solution.clone()) {
+                Ok(()) => ControlFlow::Continue(()),
+                Err(_) => ControlFlow::Break(()),
             }
+        })
+    });
 
-            // read the solutions from the device
-            solutions_buffer.read(&mut solutions).enq()?;
+    GpuSearch {
+        solutions,
+        stop_flag,
+        thread,
+    }
+}
 
-            // record the end time of the work and compute how long the work took
-            now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-            work_duration_millis = (now.as_secs() * 1000 + now.subsec_nanos() as u64 / 1000000)
-                - work_start_time_millis;
+/// The result of a [`benchmark`] run.
+#[derive(Clone, Copy, Debug)]
+pub struct Benchmark {
+    /// The number of addresses that were checked after the warm-up.
+    pub hashes: u128,
+    /// The wall-clock duration of the run after the warm-up in seconds.
+    pub seconds: f64,
+}
 
-            // if at least one solution is found, end the loop
-            if solutions[0] != 0 {
+impl Benchmark {
+    /// The average number of addresses checked per second.
+    pub fn hashes_per_second(&self) -> f64 {
+        self.hashes as f64 / self.seconds
+    }
+}
+
+/// How hard the thresholds of a Config are to meet, see [`difficulty`].
+#[derive(Clone, Copy, Debug)]
+pub struct Difficulty {
+    /// The probability of a single address meeting the thresholds.
+    pub probability: f64,
+}
+
+impl Difficulty {
+    /// The number of addresses expected to be checked per solution.
+    pub fn expected_attempts(&self) -> f64 {
+        1.0 / self.probability
+    }
+
+    /// The expected time to a solution at `rate` million addresses per
+    /// second, e.g. `~2h14m`.
+    pub fn eta(&self, rate: f64) -> String {
+        format_eta(rate, self.probability)
+    }
+}
+
+/// Computes how likely an address is to meet the Config's thresholds, the
+/// same estimate the dashboard bases its ETA on. The checksum filters can't
+/// be accounted for and are ignored.
+pub fn difficulty(config: &Config) -> Difficulty {
+    Difficulty {
+        probability: success_probability(config),
+    }
+}
+
+/// Measures the throughput of the configured devices by running the regular
+/// search for `duration` against a success condition that can never be met,
+/// after the first batches of every device warmed it up. The thresholds of
+/// the Config are ignored.
+pub fn benchmark(mut config: Config, duration: Duration) -> ocl::Result<Benchmark> {
+    measure(&mut config, duration)
+}
+
+/// Runs [`benchmark`] with the work size fitted in place, so the caller sees
+/// the work size that was measured.
+fn measure(config: &mut Config, duration: Duration) -> ocl::Result<Benchmark> {
+    let rewards = Reward::new();
+    let platform = default_platform()?;
+    fit_work_size(config, platform)?;
+    let config = &*config;
+    let kernel_src = mk_kernel_src_with_condition(config, "false");
+    let mut rngs: Vec<StdRng> = config
+        .gpu_devices
+        .iter()
+        .map(|&device| search_rng(config, device))
+        .collect();
+    let progress: Vec<Mutex<SearchState>> = rngs
+        .iter_mut()
+        .map(|rng| Mutex::new(SearchState::random(rng, config.salt_bytes)))
+        .collect();
+    let shared = Shared {
+        stop_flag: config.stop_flag.clone().unwrap_or_default(),
+        done: AtomicBool::new(false),
+        deadline: None,
+        found: Mutex::new(Found {
+            count: 0,
+            list: vec![],
+            seen: FxHashSet::default(),
+            duplicates: 0,
+            rejected: 0,
+            ended: false,
+            histogram: ZeroHistogram::default(),
+            colors: false,
+            on_solution: |_: &Solution| ControlFlow::Continue(()),
+        }),
+    };
+
+    let batches = || -> u64 {
+        progress
+            .iter()
+            .map(|state| state.lock().unwrap().cumulative_nonce)
+            .sum()
+    };
+    // the time and the batches once every device warmed up, which the
+    // measurement starts from
+    let mut warmed_up = (Instant::now(), 0);
+    let result = std::thread::scope(|scope| {
+        let workers = spawn_miners(
+            scope,
+            config,
+            &rewards,
+            platform,
+            &kernel_src,
+            &progress,
+            rngs,
+            &shared,
+        );
+        let running = || !shared.should_stop() && !workers.iter().all(|w| w.is_finished());
+
+        let warmup = WARMUP_BATCHES * config.gpu_devices.len() as u64;
+        while batches() < warmup && running() {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        warmed_up = (Instant::now(), batches());
+
+        while warmed_up.0.elapsed() < duration && running() {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        shared.done.store(true, Ordering::Relaxed);
+
+        join_miners(workers)
+    });
+    let seconds = warmed_up.0.elapsed().as_secs_f64();
+    result?;
+
+    let measured_batches = batches() - warmed_up.1;
+    Ok(Benchmark {
+        hashes: measured_batches as u128
+            * config.work_size as u128
+            * create1_nonces(config) as u128
+            * config.init_code_hashes.len() as u128,
+        seconds,
+    })
+}
+
+/// Sets the work size to the fastest of the powers of two between the
+/// minimum work size and [`MAX_AUTOTUNE_WORK_SIZE`], measuring each for
+/// [`AUTOTUNE_DURATION`]. Work sizes are rounded to fit the devices as with
+/// `auto_clamp`, and the first one the devices fail to run ends the sweep.
+fn autotune(config: &mut Config, term: &Term) -> ocl::Result<()> {
+    let auto_clamp = config.auto_clamp;
+    config.auto_clamp = true;
+
+    let mut best: Option<(u32, f64)> = None;
+    let mut candidate = min_work_size(config).next_power_of_two();
+    while candidate <= MAX_AUTOTUNE_WORK_SIZE
+        && !config
+            .stop_flag
+            .as_ref()
+            .map_or(false, |flag| flag.load(Ordering::Relaxed))
+    {
+        config.work_size = candidate;
+        let rate = match measure(config, AUTOTUNE_DURATION) {
+            Ok(result) => result.hashes_per_second(),
+            Err(err) if best.is_some() => {
+                warn!(work_size = candidate, %err, "work size failed, ending autotune");
                 break;
             }
+            Err(err) => return Err(err),
+        };
+        term.write_line(&format!(
+            "  {:#x}: {:.2} million hashes per second",
+            config.work_size,
+            rate / 1_000_000.0
+        ))?;
+        if best.map_or(true, |(_, best_rate)| rate > best_rate) {
+            best = Some((config.work_size, rate));
+        }
+        candidate *= 2;
+    }
 
-            // if no solution has yet been found, increment the nonce
-            nonce[0] += 1;
+    config.auto_clamp = auto_clamp;
+    if let Some((work_size, _)) = best {
+        config.work_size = work_size;
+    }
+    Ok(())
+}
 
-            // update the nonce buffer with the incremented nonce value
-            nonce_buffer = Buffer::builder()
-                .queue(ocl_pq.queue().clone())
-                .flags(MemFlags::new().read_write())
-                .len(1)
-                .copy_host_slice(&nonce)
-                .build()?;
+/// Makes sure every device can run `work_size` work items in a single batch,
+/// which needs the work size to be a multiple of the device's maximum work
+/// group size. Incompatible work sizes are rounded down if the Config allows
+/// it, otherwise an error suggesting a work size that fits is returned.
+fn fit_work_size(config: &mut Config, platform: Platform) -> ocl::Result<()> {
+    for &device_idx in &config.gpu_devices {
+        let device = device_by_idx(platform, device_idx)?;
+        if let Some(units) = compute_unit_cap(config, &device)? {
+            info!(device = device_idx, units, "capped compute units");
+        }
+        let max_wg_size = device.max_wg_size()? as u32;
+        if max_wg_size == 0 || config.work_size % max_wg_size == 0 {
+            continue;
         }
 
-        // iterate over each solution, first converting to a fixed array
-
-        if solutions[0] != 0 {
-            let create1_nonce = solutions[1];
-            let create2_nonce = solutions[0].to_le_bytes();
-            let mut create2_salt = [0u8; 32];
-            create2_salt[0..20].copy_from_slice(&config.owner[..]);
-            create2_salt[20..24].copy_from_slice(&salt[..]);
-            create2_salt[24..32].copy_from_slice(&create2_nonce);
-            let deployer = config
-                .factory
-                .create2(&create2_salt, &config.init_code_hash);
-            let address = deployer.create(create1_nonce.into());
-
-            // count total and leading zero bytes
-            let mut total = 0;
-            let mut leading = 0;
-            for (i, &b) in address.iter().enumerate() {
-                if b == 0 {
-                    total += 1;
-                } else if leading == 0 {
-                    // set leading on finding non-zero byte
-                    leading = i;
-                }
-            }
+        let fitted = config.work_size - config.work_size % max_wg_size;
+        if config.auto_clamp && fitted >= min_work_size(config) {
+            warn!(
+                device = device_idx,
+                from = config.work_size,
+                to = fitted,
+                "clamped work size"
+            );
+            config.work_size = fitted;
+            continue;
+        }
+
+        let max_alloc = match device.info(DeviceInfo::MaxMemAllocSize)? {
+            DeviceInfoResult::MaxMemAllocSize(bytes) => format!("{} MiB", bytes / 1024 / 1024),
+            other => other.to_string(),
+        };
+        return Err(format!(
+            "Work size {:#x} doesn't fit device {} ({}), which has a max work group size of {} \
+             and a max memory allocation of {}. Use a work size of {:#x} or pass --auto-clamp.",
+            config.work_size,
+            device_idx,
+            device.name()?,
+            max_wg_size,
+            max_alloc,
+            fitted
+        )
+        .into());
+    }
 
-            let key = leading * 20 + total;
-            let reward = rewards.get(&key).unwrap_or("0");
-            let output = format!(
-                "0x{} ({}) => {} => {}",
-                hex::encode(create2_salt),
-                create1_nonce - 1,
-                address,
-                reward
+    Ok(())
+}
+
+/// How many work groups of the device's maximum size `device` runs at a time
+/// under the compute unit cap of `config`, `None` without a cap. The cap is
+/// clamped to the compute units the device has.
+fn compute_unit_cap(config: &Config, device: &Device) -> ocl::Result<Option<u32>> {
+    let Some(units) = config.max_compute_units else {
+        return Ok(None);
+    };
+    let total = match device.info(DeviceInfo::MaxComputeUnits)? {
+        DeviceInfoResult::MaxComputeUnits(total) => total,
+        _ => units,
+    };
+    Ok(Some(units.min(total).max(1)))
+}
+
+/// Spawns a mining thread for every configured device, along with the threads
+/// verifying the candidates they report on the host. The verifiers finish once
+/// every device did.
+#[allow(clippy::too_many_arguments)]
+fn spawn_miners<'scope, 'env, F>(
+    scope: &'scope Scope<'scope, 'env>,
+    config: &'env Config,
+    rewards: &'env Reward,
+    platform: Platform,
+    kernel_src: &'env str,
+    progress: &'env [Mutex<SearchState>],
+    rngs: Vec<StdRng>,
+    shared: &'env Shared<F>,
+) -> Vec<ScopedJoinHandle<'scope, ocl::Result<()>>>
+where
+    F: FnMut(&Solution) -> ControlFlow<()> + Send,
+{
+    let (candidates, inbox) = mpsc::sync_channel(CANDIDATE_QUEUE);
+    let mut workers: Vec<_> = config
+        .gpu_devices
+        .iter()
+        .zip(progress)
+        .zip(rngs)
+        .map(|((&device, progress), rng)| {
+            let candidates = candidates.clone();
+            scope.spawn(move || {
+                let result = mine_device(
+                    config, platform, device, kernel_src, progress, rng, candidates, shared,
+                );
+                // make sure the remaining devices stop too if this one failed
+                shared.done.store(true, Ordering::Relaxed);
+                result
+            })
+        })
+        .collect();
+
+    // verifying a candidate can take a few hashes or a regex, so it's spread
+    // over some threads to keep up with the devices
+    let inbox = Arc::new(Mutex::new(inbox));
+    let verifiers = std::thread::available_parallelism()
+        .map_or(1, |threads| threads.get())
+        .min(MAX_VERIFIERS);
+    for _ in 0..verifiers {
+        let inbox = inbox.clone();
+        workers.push(scope.spawn(move || {
+            verify_candidates(config, rewards, &inbox, shared);
+            Ok(())
+        }));
+    }
+
+    workers
+}
+
+/// The hits a device reported for a work batch of `salt`, four entries per
+/// hit like the kernel writes them.
+struct Candidates {
+    salt: FixedBytes<8>,
+    entries: Vec<u64>,
+}
+
+/// Checks the candidates reported by the devices on the host, handing the
+/// ones passing every filter to the callback, until the devices stopped or
+/// the callback ends the search.
+fn verify_candidates<F>(
+    config: &Config,
+    rewards: &Reward,
+    inbox: &Mutex<Receiver<Candidates>>,
+    shared: &Shared<F>,
+) where
+    F: FnMut(&Solution) -> ControlFlow<()>,
+{
+    // the inbox is only locked while waiting, the others verify meanwhile
+    while let Ok(candidates) = inbox.lock().unwrap().recv() {
+        for entry in candidates.entries.chunks_exact(4) {
+            let solution = mk_solution(
+                config,
+                rewards,
+                &candidates.salt,
+                entry[0],
+                entry[1],
+                entry[2] as usize,
+                entry[3],
             );
 
-            let show = format!("{output} ({leading} / {total})");
-            found_list.push(show.to_string());
+            // the kernel's conditions and salt derivation are reimplemented
+            // on the host, so a mismatch points at a bug in either of them
+            if !meets_conditions(config, &solution.address) {
+                // the prefixes' Bloom filter lets some addresses through
+                // that start with none of them
+                if !PrefixBloom::needed(&config.prefixes) {
+                    warn!(
+                        address = %solution.address,
+                        variant = ?config.create3_variant,
+                        "discarded solution failing verification"
+                    );
+                }
+                continue;
+            }
+            let passes = matches_checksum(config, &solution.address)
+                && in_address_range(config, &solution.address)
+                && meets_min_reward_score(config, &solution);
 
-            file.lock_exclusive().expect("Couldn't lock file.");
+            // solutions of all devices are handled one at a time, and not at
+            // all once the callback ended the search
+            let mut found = shared.found.lock().unwrap();
+            if found.ended {
+                return;
+            }
+            if !passes {
+                found.rejected += 1;
+                continue;
+            }
+            if !found.seen.insert(solution.create2_salt) {
+                found.duplicates += 1;
+                continue;
+            }
 
-            writeln!(&file, "{output}")
-                .unwrap_or_else(|_| panic!("Couldn't write to `{}` file.", config.output_file));
+            log_solution(&solution);
+            let show = format!(
+                "{solution} ({} / {}, {} leading zero nibbles, {} uppercase)",
+                solution.leading_zeros,
+                solution.total_zeros,
+                solution.leading_zero_nibbles,
+                solution.uppercase_letters
+            );
+            let key = solution.leading_zeros * 20 + solution.total_zeros;
+            let style = output::rarity_style(rewards.rarity(&key), found.colors);
+            found.list.push(style.apply_to(show).to_string());
+            found.count += 1;
+            found.histogram.record(solution.total_zeros);
 
-            file.unlock().expect("Couldn't unlock file.");
-            found += 1;
+            if (found.on_solution)(&solution).is_break() {
+                found.ended = true;
+                shared.done.store(true, Ordering::Relaxed);
+                return;
+            }
         }
     }
 }
 
-#[track_caller]
-fn output_file(path: &str) -> File {
-    OpenOptions::new()
-        .append(true)
-        .create(true)
-        .read(true)
-        .open(path)
-        .unwrap_or_else(|_| panic!("Could not create or open `{}` file.", path))
+/// Waits for every mining thread to finish, returning the first error.
+fn join_miners(workers: Vec<ScopedJoinHandle<'_, ocl::Result<()>>>) -> ocl::Result<()> {
+    let mut result = Ok(());
+    for worker in workers {
+        let worker_result = worker.join().expect("Mining thread panicked.");
+        if let Err(err) = &worker_result {
+            warn!(%err, "device stopped with an error");
+        }
+        if result.is_ok() {
+            result = worker_result;
+        }
+    }
+    result
 }
 
-/// Creates the OpenCL kernel source code by populating the template with the
-/// values from the Config object.
-fn mk_kernel_src(config: &Config) -> String {
-    let mut src = String::with_capacity(2048 + KERNEL_SRC.len());
+/// State shared between the mining threads and the dashboard.
+struct Shared<F> {
+    /// Raised from outside of the search to stop it.
+    stop_flag: Arc<AtomicBool>,
+    /// Raised from within the search once it should end.
+    done: AtomicBool,
+    /// When the search times out, if it does.
+    deadline: Option<Instant>,
+    /// The solutions found so far by any device.
+    found: Mutex<Found<F>>,
+}
 
-    let factory = config.factory.iter();
-    let owner = config.owner.iter();
-    let hash = config.init_code_hash.iter();
-    let hash = hash.enumerate().map(|(i, x)| (i + 52, x));
-    for (i, x) in factory.chain(owner).enumerate().chain(hash) {
-        writeln!(src, "#define S_{} {}u", i + 1, x).unwrap();
+impl<F> Shared<F> {
+    fn should_stop(&self) -> bool {
+        self.stop_flag.load(Ordering::Relaxed)
+            || self.done.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .map_or(false, |deadline| Instant::now() >= deadline)
     }
+}
 
-    let lz = config.leading_zeroes_threshold.unwrap_or(0);
-    writeln!(src, "#define LEADING_ZEROES {lz}").unwrap();
-    let tz = config.total_zeroes_threshold.unwrap_or(0);
-    writeln!(src, "#define TOTAL_ZEROES {tz}").unwrap();
+struct Found<F> {
+    count: u64,
+    /// The solutions to show on the dashboard, starting with the last ones
+    /// previous runs wrote to the output file.
+    list: Vec<String>,
+    /// Salts that were already reported, by this or a previous run.
+    seen: FxHashSet<[u8; 32]>,
+    /// How many rediscovered salts were not reported again.
+    duplicates: u64,
+    /// How many candidates failed the host-side filters.
+    rejected: u64,
+    /// Whether the callback ended the search.
+    ended: bool,
+    histogram: ZeroHistogram,
+    /// Whether the lines of `list` are colored by rarity.
+    colors: bool,
+    on_solution: F,
+}
 
-    let condition = match (
-        config.leading_zeroes_threshold,
-        config.total_zeroes_threshold,
-    ) {
-        (Some(_), Some(_)) => "hasLeading(digest) || hasTotal(digest)",
-        (Some(_), None) => "hasLeading(digest)",
-        (None, Some(_)) => "hasTotal(digest)",
-        (None, None) => unreachable!(),
-    };
-    writeln!(src, "#define SUCCESS_CONDITION() {}", condition).unwrap();
+/// Searches for solutions on a single device until the search is stopped,
+/// keeping `progress` up to date with where the device currently is.
+#[allow(clippy::too_many_arguments)]
+fn mine_device<F>(
+    config: &Config,
+    platform: Platform,
+    device_idx: u8,
+    kernel_src: &str,
+    progress: &Mutex<SearchState>,
+    mut rng: StdRng,
+    candidates: SyncSender<Candidates>,
+    shared: &Shared<F>,
+) -> ocl::Result<()>
+where
+    F: FnMut(&Solution) -> ControlFlow<()>,
+{
+    let _span = info_span!("device", index = device_idx).entered();
 
-    writeln!(src, "#define MAX_NONCE {}u", config.max_create3_nonce).unwrap();
+    let ocl_pq = build_pro_que(config, platform, device_idx, kernel_src)?;
+    let prefix_bloom_buffer = prefix_bloom_buffer(config, &ocl_pq)?;
 
-    src.push_str(KERNEL_SRC);
+    // start from the state the device was set up with
+    let initial = *progress.lock().unwrap();
+    let mut next = Some(initial);
+    let mut cumulative_nonce = initial.cumulative_nonce;
 
-    src
+    // the last work duration in milliseconds
+    let mut work_duration_millis: u64 = 0;
+
+    // begin searching for addresses
+    'search: loop {
+        // construct the message to hash, leaving the rest of the salt empty,
+        // and for random searches reset the nonce to a random value for more
+        // uniformly distributed nonces
+        let start = next
+            .take()
+            .unwrap_or_else(|| SearchState::random(&mut rng, config.salt_bytes));
+        let salt = start.salt;
+
+        // build a corresponding buffer for passing the message to the kernel
+        let message_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_only())
+            .len(config.salt_bytes as usize)
+            .copy_host_slice(&salt[..config.salt_bytes as usize])
+            .build()?;
+
+        let mut nonce: [u32; 1] = [start.nonce];
+        let mut batches_on_salt: u32 = 0;
+
+        // build a corresponding buffer for passing the nonce to the kernel
+        let mut nonce_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_only())
+            .len(1)
+            .copy_host_slice(&nonce)
+            .build()?;
+
+        // establish a buffer counting the solutions found in a work batch
+        let mut solution_count: [u32; 1] = [0];
+        let solution_count_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_write())
+            .len(1)
+            .copy_host_slice(&solution_count)
+            .build()?;
+
+        // establish a buffer for nonces that result in desired addresses, each
+        // solution takes up four entries: the create2 nonce, the create1
+        // nonce, the index of the init code hash and the flags of the
+        // conditions met
+        let mut solutions: Vec<u64> = vec![0; 4 * MAX_SOLUTIONS];
+        let solutions_buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().write_only())
+            .len(solutions.len())
+            .copy_host_slice(&solutions)
+            .build()?;
+
+        // repeatedly enqueue kernel to search for new addresses
+        loop {
+            // publish the current search state for the dashboard
+            *progress.lock().unwrap() = SearchState {
+                salt,
+                nonce: nonce[0],
+                cumulative_nonce,
+            };
+
+            if shared.should_stop() {
+                break 'search;
+            }
+
+            enqueue_batch(
+                config,
+                &ocl_pq,
+                &message_buffer,
+                &nonce_buffer,
+                &solution_count_buffer,
+                &solutions_buffer,
+                &prefix_bloom_buffer,
+            )?;
+
+            // calculate the current time
+            let mut now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+            // increment the cumulative nonce (does not reset after a match)
+            cumulative_nonce += 1;
+
+            // record the start time of the work
+            let work_start_time_millis = now.as_secs() * 1000 + now.subsec_nanos() as u64 / 1000000;
+
+            // sleep for part of the previous work duration to conserve CPU
+            if work_duration_millis != 0 && config.throttle != 0 {
+                std::thread::sleep(Duration::from_millis(
+                    work_duration_millis * config.throttle as u64 * 10 / 1000,
+                ));
+            }
+
+            // read the solutions from the device
+            solution_count_buffer.read(&mut solution_count[..]).enq()?;
+
+            // record the end time of the work and compute how long the work took
+            now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+            work_duration_millis = (now.as_secs() * 1000 + now.subsec_nanos() as u64 / 1000000)
+                - work_start_time_millis;
+
+            // if at least one solution is found, end the loop
+            if solution_count[0] != 0 {
+                solutions_buffer.read(&mut solutions).enq()?;
+                break;
+            }
+
+            // if no solution has yet been found, increment the nonce, moving
+            // on to a new salt once its nonces are exhausted or the stride is
+            // used up
+            batches_on_salt += 1;
+            match next_nonce(config, nonce[0]) {
+                Some(next) if config.nonce_stride != Some(batches_on_salt) => nonce[0] = next,
+                _ => break,
+            }
+
+            // update the nonce buffer with the incremented nonce value
+            nonce_buffer = Buffer::builder()
+                .queue(ocl_pq.queue().clone())
+                .flags(MemFlags::new().read_write())
+                .len(1)
+                .copy_host_slice(&nonce)
+                .build()?;
+        }
+
+        // a sequential search picks up right after the last searched nonce,
+        // whether it had hits or exhausted the salt
+        let searched = SearchState {
+            salt,
+            nonce: nonce[0],
+            cumulative_nonce,
+        };
+        next = next_state(config, &searched, config.gpu_devices.len());
+
+        // hand the hits of the batch to the verifiers and carry on searching,
+        // the verifiers only stop early once the search is done
+        let reported = (solution_count[0] as usize).min(MAX_SOLUTIONS);
+        if reported != 0 {
+            let batch = Candidates {
+                salt,
+                entries: solutions[..4 * reported].to_vec(),
+            };
+            if candidates.send(batch).is_err() {
+                break 'search;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Compiles the kernel for a device and sets up the queue to run it on.
+fn build_pro_que(
+    config: &Config,
+    platform: Platform,
+    device_idx: u8,
+    kernel_src: &str,
+) -> ocl::Result<ProQue> {
+    // set up the device to use
+    let device = device_by_idx(platform, device_idx)?;
+    info!(name = %device.name()?, "building program");
+
+    // set up the context to use
+    let context = Context::builder()
+        .platform(platform)
+        .devices(device)
+        .build()?;
+
+    // set up the program to use, the build error carries the device's build
+    // log, whose line numbers refer to the generated source
+    let program = Program::builder()
+        .devices(device)
+        .src(kernel_src)
+        .build(&context)
+        .map_err(|err| {
+            eprintln!("{}", number_lines(kernel_src));
+            ocl::Error::from(format!(
+                "Couldn't build the kernel for device {device_idx}, its source is printed \
+                 above: {err}"
+            ))
+        })?;
+
+    info!("program built");
+
+    // set up the queue to use
+    let queue = Queue::new(&context, device, None)?;
+
+    // set up the "proqueue" (or amalgamation of various elements) to use
+    Ok(ProQue::new(context, queue, program, Some(config.work_size)))
+}
+
+/// Enqueues a single work batch, its results are written to the solution
+/// buffers once it completes. Under a compute unit cap the batch is split
+/// into launches of at most that many work groups, see
+/// [`Config::max_compute_units`].
+fn enqueue_batch(
+    config: &Config,
+    ocl_pq: &ProQue,
+    message_buffer: &Buffer<u8>,
+    nonce_buffer: &Buffer<u32>,
+    solution_count_buffer: &Buffer<u32>,
+    solutions_buffer: &Buffer<u64>,
+    prefix_bloom_buffer: &Buffer<u32>,
+) -> ocl::Result<()> {
+    // build the kernel and define the type of each buffer
+    let kern = ocl_pq
+        .kernel_builder("hashMessage")
+        .arg_named("message", None::<&Buffer<u8>>)
+        .arg_named("nonce", None::<&Buffer<u32>>)
+        .arg_named("solutionCount", None::<&Buffer<u32>>)
+        .arg_named("solutions", None::<&Buffer<u64>>)
+        .arg_named("prefixBloom", None::<&Buffer<u32>>)
+        .build()?;
+
+    // set each buffer
+    kern.set_arg("message", Some(message_buffer))?;
+    kern.set_arg("nonce", Some(nonce_buffer))?;
+    kern.set_arg("solutionCount", solution_count_buffer)?;
+    kern.set_arg("solutions", solutions_buffer)?;
+    kern.set_arg("prefixBloom", prefix_bloom_buffer)?;
+
+    // enqueue the kernel
+    let device = ocl_pq.queue().device();
+    let Some(units) = compute_unit_cap(config, &device)? else {
+        return unsafe { kern.enq() };
+    };
+    let local = device.max_wg_size()?.max(1);
+    let launch = units as usize * local;
+    let work_size = config.work_size as usize;
+    for offset in (0..work_size).step_by(launch) {
+        unsafe {
+            kern.cmd()
+                .global_work_offset(offset)
+                .global_work_size(launch.min(work_size - offset))
+                .local_work_size(local)
+                .enq()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Uploads the Bloom filter of the prefixes for the kernel to check addresses
+/// against, a single unused word if the prefixes fit its tables.
+fn prefix_bloom_buffer(config: &Config, ocl_pq: &ProQue) -> ocl::Result<Buffer<u32>> {
+    let words = PrefixBloom::new(&config.prefixes).map_or(vec![0], |bloom| bloom.words);
+    Buffer::builder()
+        .queue(ocl_pq.queue().clone())
+        .flags(MemFlags::new().read_only())
+        .len(words.len())
+        .copy_host_slice(&words)
+        .build()
+}
+
+/// Searches a single work batch of `work_size` salts for the random `salt`
+/// segment at `nonce` on the first configured device, returning the
+/// solutions it found, if any. This is the unit the search loop is made of,
+/// for driving the search manually.
+pub fn search_batch(
+    config: &Config,
+    salt: FixedBytes<8>,
+    nonce: u32,
+) -> ocl::Result<Vec<Solution>> {
+    let rewards = load_rewards(config)?;
+    let platform = default_platform()?;
+    let device_idx = *config
+        .gpu_devices
+        .first()
+        .ok_or_else(|| "Must specify at least one GPU device".to_string())?;
+    let ocl_pq = build_pro_que(config, platform, device_idx, &mk_kernel_src(config))?;
+
+    Ok(run_batch(config, &ocl_pq, &salt, nonce)?
+        .chunks_exact(4)
+        .map(|entry| {
+            mk_solution(
+                config,
+                &rewards,
+                &salt,
+                entry[0],
+                entry[1],
+                entry[2] as usize,
+                entry[3],
+            )
+        })
+        .filter(|solution| {
+            meets_conditions(config, &solution.address)
+                && matches_checksum(config, &solution.address)
+                && in_address_range(config, &solution.address)
+                && meets_min_reward_score(config, solution)
+        })
+        .collect())
+}
+
+/// Prefixes every line of `src` with its line number, for reading build logs.
+fn number_lines(src: &str) -> String {
+    let width = src.lines().count().to_string().len();
+    src.lines()
+        .enumerate()
+        .map(|(i, line)| format!("{:>width$} | {line}", i + 1))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Runs a single work batch of the program in `ocl_pq`, returning the create2
+/// nonce, create1 nonce, initcode hash index and condition flags of every
+/// reported hit.
+fn run_batch(
+    config: &Config,
+    ocl_pq: &ProQue,
+    salt: &FixedBytes<8>,
+    nonce: u32,
+) -> ocl::Result<Vec<u64>> {
+    let message_buffer = Buffer::builder()
+        .queue(ocl_pq.queue().clone())
+        .flags(MemFlags::new().read_only())
+        .len(config.salt_bytes as usize)
+        .copy_host_slice(&salt[..config.salt_bytes as usize])
+        .build()?;
+    let nonce = [nonce];
+    let nonce_buffer = Buffer::builder()
+        .queue(ocl_pq.queue().clone())
+        .flags(MemFlags::new().read_only())
+        .len(1)
+        .copy_host_slice(&nonce)
+        .build()?;
+    let mut solution_count: [u32; 1] = [0];
+    let solution_count_buffer = Buffer::builder()
+        .queue(ocl_pq.queue().clone())
+        .flags(MemFlags::new().read_write())
+        .len(1)
+        .copy_host_slice(&solution_count)
+        .build()?;
+    let mut solutions: Vec<u64> = vec![0; 4 * MAX_SOLUTIONS];
+    let solutions_buffer = Buffer::builder()
+        .queue(ocl_pq.queue().clone())
+        .flags(MemFlags::new().write_only())
+        .len(solutions.len())
+        .copy_host_slice(&solutions)
+        .build()?;
+
+    let prefix_bloom_buffer = prefix_bloom_buffer(config, ocl_pq)?;
+
+    enqueue_batch(
+        config,
+        ocl_pq,
+        &message_buffer,
+        &nonce_buffer,
+        &solution_count_buffer,
+        &solutions_buffer,
+        &prefix_bloom_buffer,
+    )?;
+    solution_count_buffer.read(&mut solution_count[..]).enq()?;
+    solutions_buffer.read(&mut solutions).enq()?;
+
+    let reported = (solution_count[0] as usize).min(MAX_SOLUTIONS);
+    solutions.truncate(4 * reported);
+    Ok(solutions)
+}
+
+/// Checks the device's keccak implementation by having it derive a few
+/// addresses of a fixed salt and comparing them against the host's. A broken
+/// OpenCL compiler or driver would otherwise report salts that don't deploy to
+/// the addresses shown. Every create3 variant is checked where the salt is
+/// bound to the owner, as each hashes the salt its own way.
+fn self_test(config: &mut Config, platform: Platform, device_idx: u8) -> ocl::Result<()> {
+    let configured = config.create3_variant;
+    let variants =
+        if config.mode == Mode::Create3 && config.salt_prefix.is_none() && !config.free_salt {
+            vec![
+                Create3Variant::Default,
+                Create3Variant::Solmate,
+                Create3Variant::CreateX,
+            ]
+        } else {
+            vec![configured]
+        };
+    for variant in variants {
+        config.create3_variant = variant;
+        let result = self_test_variant(config, platform, device_idx);
+        config.create3_variant = configured;
+        result?;
+    }
+    info!(device = device_idx, "keccak self-test passed");
+
+    Ok(())
+}
+
+/// Runs the self-test for the create3 variant `config` is set to.
+fn self_test_variant(config: &Config, platform: Platform, device_idx: u8) -> ocl::Result<()> {
+    let start = SearchState::random(&mut StdRng::seed_from_u64(0), config.salt_bytes);
+    let hash_index = config.init_code_hashes.len() - 1;
+    let create1_nonce = *proxy_nonces(config).last().unwrap() as u64;
+
+    // check the first, second and last work item, with the last initcode hash
+    // and create1 nonce so every loop of the kernel is exercised
+    let mut expected: Vec<u64> = [0, 1, config.work_size - 1]
+        .iter()
+        .map(|&id| (start.nonce as u64) << 32 | id as u64)
+        .collect();
+    let condition: Vec<String> = expected
+        .iter()
+        .map(|&create2_nonce| {
+            let (address, _) = reconstruct_address(
+                config,
+                &start.salt,
+                create2_nonce,
+                &config.init_code_hashes[hash_index],
+                create1_nonce,
+            );
+            let bytes: Vec<String> = address
+                .iter()
+                .enumerate()
+                .map(|(i, b)| format!("digest[{i}] == {b}u"))
+                .collect();
+            format!(
+                "(get_global_id(0) == {}u && hashIndex == {}u && create1Nonce == {}u && {})",
+                create2_nonce as u32,
+                hash_index,
+                create1_nonce,
+                bytes.join(" && ")
+            )
+        })
+        .collect();
+
+    let kernel_src = mk_kernel_src_with_condition(config, &condition.join(" || "));
+    let ocl_pq = build_pro_que(config, platform, device_idx, &kernel_src)?;
+    let mut reproduced: Vec<u64> = run_batch(config, &ocl_pq, &start.salt, start.nonce)?
+        .chunks_exact(4)
+        .map(|entry| entry[0])
+        .collect();
+
+    expected.sort_unstable();
+    reproduced.sort_unstable();
+    if reproduced != expected {
+        return Err(format!(
+            "Keccak self-test failed on device {} with the {:?} create3 variant: the kernel \
+             reproduced {} of {} known addresses, its results can't be trusted",
+            device_idx,
+            config.create3_variant,
+            reproduced.len(),
+            expected.len()
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Searches a single work batch at a random salt and nonce, appending its
+/// solutions to the output file like [`gpu`] does, then returns.
+pub fn once(mut config: Config) -> ocl::Result<()> {
+    let term = output::status_term(&config.output_file);
+    draw_free_salt(&mut config);
+    if let Some(warning) = describe_free_salt(&config) {
+        term.write_line(&warning)?;
+    }
+    let device_idx = config.gpu_devices.first().copied().unwrap_or_default();
+    let start = config.start.unwrap_or_else(|| {
+        SearchState::random(&mut search_rng(&config, device_idx), config.salt_bytes)
+    });
+    let solutions = search_batch(&config, start.salt, start.nonce)?;
+
+    let file = OutputFile::open(&config)?;
+    for solution in &solutions {
+        if !file.is_stdout() {
+            println!("{solution}");
+        }
+        file.write(solution);
+    }
+    file.close();
+    term.write_line(&format!(
+        "Searched salt {} at nonce {}, found: {}",
+        start.salt,
+        start.nonce,
+        solutions.len()
+    ))?;
+
+    Ok(())
+}
+
+/// Whether `address` meets each of the kernel's predicates, in the order of
+/// [`CONDITION_NAMES`], `None` for those the Config doesn't set.
+fn conditions(config: &Config, address: &Address) -> [Option<bool>; 6] {
+    let leading = address.iter().take_while(|&&b| b == 0).count();
+    let total = address.iter().filter(|&&b| b == 0).count();
+    [
+        config
+            .leading_zeroes_threshold
+            .map(|threshold| leading >= threshold as usize),
+        config
+            .total_zeroes_threshold
+            .map(|threshold| total >= threshold as usize),
+        config
+            .leading_zero_nibbles_threshold
+            .map(|threshold| leading_zero_nibbles(address) >= threshold as usize),
+        config
+            .repeat_run
+            .map(|run| longest_repeat_run(address) >= run as usize),
+        (!config.prefixes.is_empty())
+            .then(|| config.prefixes.iter().any(|prefix| prefix.matches(address))),
+        (!config.match_bytes.is_empty())
+            .then(|| config.match_bytes.iter().all(|byte| byte.matches(address))),
+    ]
+}
+
+/// Host-side equivalent of the kernel's `SUCCESS_CONDITION`.
+pub(crate) fn meets_conditions(config: &Config, address: &Address) -> bool {
+    let conditions = conditions(config, address);
+    let mut set = conditions.iter().flatten().peekable();
+    if set.peek().is_none() {
+        // without thresholds every address is left to the checksum regex
+        true
+    } else if config.require_all {
+        set.all(|&met| met)
+    } else {
+        set.any(|&met| met)
+    }
+}
+
+/// Host-side equivalent of the kernel's `MATCHED_FLAGS`, the flags word
+/// with a bit of [`CONDITION_NAMES`] set for every predicate `address` meets.
+pub(crate) fn condition_flags(config: &Config, address: &Address) -> u64 {
+    conditions(config, address)
+        .iter()
+        .enumerate()
+        .filter(|(_, met)| **met == Some(true))
+        .fold(0, |flags, (bit, _)| flags | 1 << bit)
+}
+
+/// The names of the conditions set in the `flags` word the kernel reported
+/// for `address`, so it's clear which of several OR'd conditions a solution
+/// was found for: `leading_zeros`, `total_zeros`, `leading_nibbles`,
+/// `repeat_run`, `prefix:N` for the `N`th prefix of the list and
+/// `match_bytes`.
+pub(crate) fn matched_conditions(config: &Config, address: &Address, flags: u64) -> Vec<String> {
+    let mut matched = vec![];
+    for (bit, &name) in CONDITION_NAMES.iter().enumerate() {
+        if flags & 1 << bit == 0 {
+            continue;
+        }
+        if name == "prefix" {
+            // the kernel can't tell which prefix matched when it checks them
+            // against a Bloom filter, so the host looks them up
+            for (i, prefix) in config.prefixes.iter().enumerate() {
+                if prefix.matches(address) {
+                    matched.push(format!("prefix:{i}"));
+                }
+            }
+        } else {
+            matched.push(name.to_string());
+        }
+    }
+    matched
+}
+
+/// Whether the checksummed `address` matches the Config's checksum regex and
+/// has enough uppercase letters, if either is set. Checksumming hashes the
+/// address again, so this only runs on addresses that already passed the
+/// thresholds.
+pub(crate) fn matches_checksum(config: &Config, address: &Address) -> bool {
+    if config.checksum_regex.is_none() && config.min_uppercase.is_none() {
+        return true;
+    }
+    let checksum = address.to_checksum(None);
+    config
+        .checksum_regex
+        .as_ref()
+        .map_or(true, |regex| regex.is_match(&checksum))
+        && config
+            .min_uppercase
+            .map_or(true, |min| uppercase_letters(&checksum) >= min as usize)
+}
+
+/// Whether `address` lies in the Config's address range, if one is set.
+pub(crate) fn in_address_range(config: &Config, address: &Address) -> bool {
+    config
+        .address_range
+        .map_or(true, |range| range.contains(address))
+}
+
+/// The number of uppercase letters in a checksummed address.
+fn uppercase_letters(checksum: &str) -> usize {
+    checksum.chars().filter(char::is_ascii_uppercase).count()
+}
+
+/// Whether `solution` is rare enough to be reported under the Config's minimum
+/// reward score, if one is set.
+pub(crate) fn meets_min_reward_score(config: &Config, solution: &Solution) -> bool {
+    config
+        .min_reward_score
+        .map_or(true, |min| solution.reward_score >= min)
+}
+
+/// Describes the conditions a solution has to meet for the dashboard.
+fn describe_conditions(config: &Config) -> String {
+    let mut conditions: Vec<String> = [
+        (config.leading_zeroes_threshold, "leading zero bytes"),
+        (config.total_zeroes_threshold, "total zero bytes"),
+        (
+            config.leading_zero_nibbles_threshold,
+            "leading zero nibbles",
+        ),
+        (config.repeat_run, "repeated nibbles"),
+    ]
+    .iter()
+    .filter_map(|(threshold, name)| threshold.map(|threshold| format!("{threshold} {name}")))
+    .collect();
+    match config.prefixes.as_slice() {
+        [] => {}
+        [prefix] => conditions.push(format!("prefix {prefix}")),
+        prefixes => conditions.push(format!("one of {} prefixes", prefixes.len())),
+    }
+    if !config.match_bytes.is_empty() {
+        let bytes: Vec<String> = config.match_bytes.iter().map(ToString::to_string).collect();
+        conditions.push(format!("bytes {}", bytes.join(", ")));
+    }
+    let mut description = conditions.join(if config.require_all { " and " } else { " or " });
+    if let Some(regex) = &config.checksum_regex {
+        if !description.is_empty() {
+            description.push_str(", then ");
+        }
+        write!(description, "checksum matching /{regex}/").unwrap();
+    }
+    if let Some(min) = config.min_uppercase {
+        if !description.is_empty() {
+            description.push_str(", then ");
+        }
+        write!(description, "{min} uppercase letters").unwrap();
+    }
+    if let Some(range) = config.address_range {
+        if !description.is_empty() {
+            description.push_str(", then ");
+        }
+        write!(description, "address in {range}").unwrap();
+    }
+    description
+}
+
+/// The number of zero nibbles `address` starts with.
+fn leading_zero_nibbles(address: &Address) -> usize {
+    let leading = address.iter().take_while(|&&b| b == 0).count();
+    match address.get(leading) {
+        Some(b) if b >> 4 == 0 => leading * 2 + 1,
+        _ => leading * 2,
+    }
+}
+
+/// The length of the longest run of identical nibbles in `address`.
+fn longest_repeat_run(address: &Address) -> usize {
+    let nibbles = address.iter().flat_map(|b| [b >> 4, b & 0x0f]);
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous = None;
+    for nibble in nibbles {
+        run = if previous == Some(nibble) { run + 1 } else { 1 };
+        longest = longest.max(run);
+        previous = Some(nibble);
+    }
+    longest
+}
+
+/// The rate in millions of attempts per second, given the attempts made (in
+/// millions) over an `elapsed` interval.
+fn attempt_rate(attempts: u128, elapsed: Duration) -> f64 {
+    let seconds = elapsed.as_secs_f64();
+    if seconds > 0.0 {
+        attempts as f64 / seconds
+    } else {
+        0.0
+    }
+}
+
+/// The probability of a single address meeting the thresholds, treating them
+/// as independent. The checksum regex can't be accounted for and is ignored.
+fn success_probability(config: &Config) -> f64 {
+    let total_zeros = |threshold: u8| {
+        // binomial over the 20 bytes of the address, each zero with 1/256
+        let p: f64 = 1.0 / 256.0;
+        (threshold as i32..=20)
+            .map(|k| {
+                let choose = (0..k).fold(1.0, |acc, i| acc * (20 - i) as f64 / (i + 1) as f64);
+                choose * p.powi(k) * (1.0 - p).powi(20 - k)
+            })
+            .sum::<f64>()
+    };
+    let repeat_run = |run: u8| {
+        // a run can start at any of the 40 nibbles, after the first only if
+        // the nibble before it differs
+        let run = run as i32;
+        let starts = 1.0 + (40 - run) as f64 * 15.0 / 16.0;
+        (starts * 16f64.powi(1 - run)).min(1.0)
+    };
+    let probabilities: Vec<f64> = [
+        config
+            .leading_zeroes_threshold
+            .map(|threshold| 256f64.powi(-(threshold as i32))),
+        config.total_zeroes_threshold.map(total_zeros),
+        config
+            .leading_zero_nibbles_threshold
+            .map(|threshold| 16f64.powi(-(threshold as i32))),
+        config.repeat_run.map(repeat_run),
+        // overlapping prefixes are counted twice
+        (!config.prefixes.is_empty()).then(|| {
+            config
+                .prefixes
+                .iter()
+                .map(|prefix| 16f64.powi(-(prefix.nibbles() as i32)))
+                .sum::<f64>()
+                .min(1.0)
+        }),
+        (!config.match_bytes.is_empty()).then(|| 256f64.powi(-(config.match_bytes.len() as i32))),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    if probabilities.is_empty() {
+        1.0
+    } else if config.require_all {
+        probabilities.iter().product()
+    } else {
+        1.0 - probabilities.iter().map(|p| 1.0 - p).product::<f64>()
+    }
+}
+
+/// Formats the expected time to the next solution at `rate` million attempts
+/// per second, e.g. `~2h14m`.
+fn format_eta(rate: f64, probability: f64) -> String {
+    let seconds = 1.0 / (probability * rate * 1_000_000.0);
+    if !seconds.is_finite() {
+        return "unknown".to_string();
+    }
+
+    let seconds = seconds as u64;
+    match (seconds / 86400, seconds % 86400 / 3600, seconds % 3600 / 60) {
+        (0, 0, 0) => format!("~{seconds}s"),
+        (0, 0, mins) => format!("~{}m{:02}s", mins, seconds % 60),
+        (0, hrs, mins) => format!("~{hrs}h{mins:02}m"),
+        (days, hrs, _) => format!("~{days}d{hrs:02}h"),
+    }
+}
+
+/// Breaks the search once `written` reaches the configured maximum, if any.
+pub(crate) fn enough_results(max_results: Option<usize>, written: usize) -> ControlFlow<()> {
+    match max_results {
+        Some(max) if written >= max => ControlFlow::Break(()),
+        _ => ControlFlow::Continue(()),
+    }
+}
+
+/// The reward tier of `stop_at_reward`, resolved against the reward table.
+pub(crate) struct RewardTarget {
+    rewards: Reward,
+    tier: f64,
+}
+
+impl RewardTarget {
+    /// Resolves the Config's `stop_at_reward`, if set, failing if it is
+    /// neither a number nor a reward of the table.
+    pub(crate) fn new(config: &Config) -> std::io::Result<Option<Self>> {
+        let Some(reward) = &config.stop_at_reward else {
+            return Ok(None);
+        };
+        let rewards = load_rewards(config)?;
+        let tier = rewards.tier(reward).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Stop reward `{reward}` is neither a number nor a reward of the table"),
+            )
+        })?;
+        Ok(Some(RewardTarget { rewards, tier }))
+    }
+
+    /// Whether `solution` is worth at least the tier.
+    pub(crate) fn reached(&self, solution: &Solution) -> bool {
+        let key = solution.leading_zeros * 20 + solution.total_zeros;
+        self.rewards.rarity(&key) >= self.tier
+    }
+}
+
+/// Emits a structured event for a found solution.
+pub(crate) fn log_solution(solution: &Solution) {
+    info!(
+        salt = %format_args!("0x{}", hex::encode(solution.create2_salt)),
+        create3_nonce = solution.nonce,
+        address = %solution.address,
+        leading_zeros = solution.leading_zeros,
+        leading_zero_nibbles = solution.leading_zero_nibbles,
+        uppercase_letters = solution.uppercase_letters,
+        total_zeros = solution.total_zeros,
+        reward = %solution.reward,
+        "found solution"
+    );
+}
+
+/// Loads the reward table configured in the Config, defaulting to the
+/// built-in one.
+pub(crate) fn load_rewards(config: &Config) -> std::io::Result<Reward> {
+    match &config.reward_file {
+        Some(path) => Reward::from_file(path),
+        None => Ok(Reward::new()),
+    }
+}
+
+/// The create1 nonces checked per salt, in the order they're checked. The
+/// create2 mode checks the salt's address exactly once and always reports a
+/// nonce of 1. Factories of a non-default variant only ever deploy at the
+/// first nonce.
+pub(crate) fn proxy_nonces(config: &Config) -> Vec<u8> {
+    match (config.mode, config.create3_variant) {
+        (Mode::Create3, Create3Variant::Default) => match &config.proxy_nonces {
+            Some(nonces) => nonces.clone(),
+            None => (1..=config.max_create3_nonce).collect(),
+        },
+        (Mode::Create3, _) | (Mode::Create2, _) => vec![1],
+    }
+}
+
+/// How many create1 nonces are checked per salt, see [`proxy_nonces`].
+pub(crate) fn create1_nonces(config: &Config) -> u8 {
+    proxy_nonces(config).len() as u8
+}
+
+/// Explains which nonces the deploy proxy is searched at and how they're
+/// reported, nothing in create2 mode as there's no proxy.
+pub(crate) fn describe_nonces(config: &Config) -> Option<String> {
+    if config.mode == Mode::Create2 {
+        return None;
+    }
+    let nonces = proxy_nonces(config);
+    let (create1, create3) = match &config.proxy_nonces {
+        Some(_) => {
+            let list = |offset: u8| -> String {
+                let nonces: Vec<String> = nonces.iter().map(|n| (n - offset).to_string()).collect();
+                nonces.join(", ")
+            };
+            (list(0), list(1))
+        }
+        _ => (
+            format!("1..={}", nonces.len()),
+            format!("0..={}", nonces.len() - 1),
+        ),
+    };
+    Some(format!(
+        "Enumerating the deploy proxy's create1 (RLP) nonces {create1}, solutions report the \
+         create3 nonce to pass to the factory, which is the create1 nonce minus one: {create3}"
+    ))
+}
+
+/// The state the miner in `slot` of the configured devices starts a fresh
+/// search at.
+pub(crate) fn initial_state(config: &Config, rng: &mut StdRng, slot: usize) -> SearchState {
+    if let Some(start) = config.start {
+        return start;
+    }
+    match config.salt_mode {
+        SaltMode::Random => SearchState::random(rng, config.salt_bytes),
+        SaltMode::Sequential => SearchState::sequential(
+            config.salt_start.wrapping_add(slot as u64),
+            config.salt_bytes,
+        ),
+    }
+}
+
+/// The state a sequential search continues at once the nonce of `searched`
+/// was searched: the salt's next nonce, or the first nonce of the salt
+/// `stride` salts further once its nonces are exhausted. `None` for random
+/// searches, which draw a new salt instead.
+pub(crate) fn next_state(
+    config: &Config,
+    searched: &SearchState,
+    stride: usize,
+) -> Option<SearchState> {
+    if config.salt_mode == SaltMode::Random {
+        return None;
+    }
+    if let Some(nonce) = next_nonce(config, searched.nonce) {
+        return Some(SearchState { nonce, ..*searched });
+    }
+    let counter = searched
+        .counter(config.salt_bytes)
+        .wrapping_add(stride as u64);
+    Some(SearchState::sequential(counter, config.salt_bytes))
+}
+
+/// The random number generator a miner draws its salts and nonces from,
+/// seeded per `stream` if the Config sets a seed so that every miner searches
+/// a reproducible but distinct sequence of salts. The stream is spread over
+/// the whole seed rather than added to it, so runs with adjacent seeds don't
+/// share sequences between their devices.
+pub(crate) fn search_rng(config: &Config, stream: u8) -> StdRng {
+    match config.seed {
+        Some(seed) => {
+            StdRng::seed_from_u64(seed ^ (stream as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+        }
+        None => StdRng::from_entropy(),
+    }
+}
+
+/// Sets the salt prefix to 20 random bytes for a search with `free_salt`,
+/// drawn from the seed if there is one.
+pub(crate) fn draw_free_salt(config: &mut Config) {
+    if config.free_salt && config.salt_prefix.is_none() {
+        let mut prefix = [0u8; 20];
+        search_rng(config, u8::MAX).fill(&mut prefix);
+        config.salt_prefix = Some(Bytes::copy_from_slice(&prefix));
+    }
+}
+
+/// The warning for searches whose salts aren't bound to the caller.
+pub(crate) fn describe_free_salt(config: &Config) -> Option<String> {
+    config.free_salt.then(|| {
+        format!(
+            "WARNING: salts start with 0x{} rather than the owner and aren't bound to the caller, \
+             anyone can deploy with them and front-run the deployment.",
+            hex::encode(salt_prefix(config))
+        )
+    })
+}
+
+/// The first 20 bytes of every salt, the owner unless a salt prefix is set.
+pub(crate) fn salt_prefix(config: &Config) -> [u8; 20] {
+    match &config.salt_prefix {
+        Some(prefix) => {
+            let mut padded = [0u8; 20];
+            padded[..prefix.len()].copy_from_slice(prefix);
+            padded
+        }
+        None => config.owner.into(),
+    }
+}
+
+/// Assembles the full 32-byte salt from the owner (or salt prefix), the random
+/// segment and as much of the nonce the kernel reported as still fits.
+pub(crate) fn create2_salt(config: &Config, salt: &FixedBytes<8>, create2_nonce: u64) -> [u8; 32] {
+    let salt_bytes = config.salt_bytes as usize;
+    let mut create2_salt = [0u8; 32];
+    create2_salt[0..20].copy_from_slice(&salt_prefix(config));
+    create2_salt[20..20 + salt_bytes].copy_from_slice(&salt[..salt_bytes]);
+    create2_salt[20 + salt_bytes..32]
+        .copy_from_slice(&create2_nonce.to_le_bytes()[..12 - salt_bytes]);
+    if config.create3_variant == Create3Variant::CreateX {
+        // CreateX only hashes in the caller if the byte after it is 0
+        create2_salt[20] = 0;
+    }
+    create2_salt
+}
+
+/// The salt the factory creates the deploy proxy with when called with
+/// `create2_salt`.
+pub(crate) fn factory_salt(config: &Config, create2_salt: &[u8; 32]) -> FixedBytes<32> {
+    match config.create3_variant {
+        Create3Variant::Default => create2_salt.into(),
+        Create3Variant::Solmate => keccak256([config.owner.as_slice(), create2_salt].concat()),
+        Create3Variant::CreateX => {
+            let mut preimage = [0u8; 64];
+            preimage[12..32].copy_from_slice(config.owner.as_slice());
+            preimage[32..].copy_from_slice(create2_salt);
+            keccak256(preimage)
+        }
+    }
+}
+
+/// The nonce after `nonce` of the same salt, `None` once the salt's nonces are
+/// exhausted. Never wraps around at `u32::MAX`, and also ends the salt if
+/// `nonce` already lies past its last nonce, as one resumed from a search with
+/// fewer salt bytes can.
+pub(crate) fn next_nonce(config: &Config, nonce: u32) -> Option<u32> {
+    nonce
+        .checked_add(1)
+        .filter(|&next| (next as u64) < nonce_limit(config.salt_bytes))
+}
+
+/// The smallest work size the Config allows.
+pub(crate) fn min_work_size(config: &Config) -> u32 {
+    if config.allow_small_work_size {
+        MIN_SMALL_WORK_SIZE
+    } else {
+        MIN_WORK_SIZE
+    }
+}
+
+/// How many nonces a random salt of `salt_bytes` bytes has before the nonce
+/// segment is exhausted.
+pub(crate) fn nonce_limit(salt_bytes: u8) -> u64 {
+    1 << (8 * (8 - salt_bytes as u64))
+}
+
+/// The address the factory creates the deploy proxy at `init_code_hash` at
+/// when called with `create2_salt`.
+pub(crate) fn proxy_address(
+    config: &Config,
+    create2_salt: &[u8; 32],
+    init_code_hash: FixedBytes<32>,
+) -> Address {
+    config
+        .factory
+        .create2(factory_salt(config, create2_salt), init_code_hash)
+}
+
+/// The address the factory deploys to when called with `create2_salt`, with
+/// the deploy proxy at `init_code_hash` deploying at `create1_nonce`.
+pub(crate) fn derive_address(
+    config: &Config,
+    create2_salt: &[u8; 32],
+    create1_nonce: u64,
+    init_code_hash: FixedBytes<32>,
+) -> Address {
+    let deployer = proxy_address(config, create2_salt, init_code_hash);
+    match config.mode {
+        Mode::Create3 => deployer.create(create1_nonce),
+        Mode::Create2 => deployer,
+    }
+}
+
+/// Reconstructs the address a miner found at `create2_nonce` of the random
+/// `salt` from everything the kernel reports about it, along with the full
+/// salt to call the factory with. This is the host's reimplementation of the
+/// kernel's address derivation, shared by everything checking its results.
+pub fn reconstruct_address(
+    config: &Config,
+    salt: &FixedBytes<8>,
+    create2_nonce: u64,
+    init_code_hash: &FixedBytes<32>,
+    create1_nonce: u64,
+) -> (Address, [u8; 32]) {
+    let create2_salt = create2_salt(config, salt, create2_nonce);
+    let address = derive_address(config, &create2_salt, create1_nonce, *init_code_hash);
+    (address, create2_salt)
+}
+
+/// Reconstructs the address found by a miner and rates its rarity.
+pub(crate) fn mk_solution(
+    config: &Config,
+    rewards: &Reward,
+    salt: &FixedBytes<8>,
+    create2_nonce: u64,
+    create1_nonce: u64,
+    hash_index: usize,
+    flags: u64,
+) -> Solution {
+    let init_code_hash = config.init_code_hashes[hash_index];
+    let (address, create2_salt) =
+        reconstruct_address(config, salt, create2_nonce, &init_code_hash, create1_nonce);
+    let proxy_address = proxy_address(config, &create2_salt, init_code_hash);
+
+    // count total and leading zero bytes
+    let total = address.iter().filter(|&&b| b == 0).count();
+    let leading = address.iter().take_while(|&&b| b == 0).count();
+    let leading_nibbles = leading_zero_nibbles(&address);
+
+    let key = leading * 20 + total;
+    let reward = rewards.get(&key).unwrap_or("0");
+    let reward_score = rewards.score(&key);
+    Solution {
+        create2_salt,
+        nonce: create1_nonce - 1,
+        address,
+        proxy_address,
+        total_zeros: total,
+        leading_zeros: leading,
+        leading_zero_nibbles: leading_nibbles,
+        uppercase_letters: uppercase_letters(&address.to_checksum(None)),
+        reward: reward.to_string(),
+        reward_score,
+        init_code_hash,
+        matched: matched_conditions(config, &address, flags),
+    }
+}
+
+/// Prints every OpenCL device of every platform along with the index it's
+/// selected by. Only the devices of the default platform can be mined on, so
+/// those are the indices `gpu_devices` refers to.
+pub fn list_devices() -> ocl::Result<()> {
+    let default_platform = default_platform()?;
+
+    for platform in Platform::list() {
+        let default = if platform.as_core() == default_platform.as_core() {
+            " (default)"
+        } else {
+            ""
+        };
+        println!(
+            "platform: {} ({}){}",
+            platform.name()?,
+            platform.vendor()?,
+            default
+        );
+
+        for (idx, device) in Device::list_all(platform)?.iter().enumerate() {
+            let memory = match device.info(DeviceInfo::GlobalMemSize)? {
+                DeviceInfoResult::GlobalMemSize(bytes) => {
+                    format!("{} MiB", bytes / 1024 / 1024)
+                }
+                other => other.to_string(),
+            };
+            println!(
+                "  device {}: {} ({}), global memory: {}, max work group size: {}",
+                idx,
+                device.name()?,
+                device.vendor()?,
+                memory,
+                device.max_wg_size()?
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// The default OpenCL platform, failing with what to install if there's no
+/// platform at all, as on machines without a GPU driver.
+pub(crate) fn default_platform() -> ocl::Result<Platform> {
+    match ocl::core::get_platform_ids() {
+        Ok(platforms) if !platforms.is_empty() => Ok(Platform::new(ocl::core::default_platform()?)),
+        _ => Err(ocl::Error::from(
+            "No OpenCL platforms found, install the GPU driver or an OpenCL ICD loader and \
+             runtime (e.g. POCL for CPUs)"
+                .to_string(),
+        )),
+    }
+}
+
+/// The device at `device_idx` of `platform`, failing with the indices there
+/// are rather than wrapping around to another device like
+/// `Device::by_idx_wrap` does.
+pub(crate) fn device_by_idx(platform: Platform, device_idx: u8) -> ocl::Result<Device> {
+    let devices = Device::list_all(platform)?;
+    devices.get(device_idx as usize).copied().ok_or_else(|| {
+        let message = match devices.len() {
+            0 => format!(
+                "No OpenCL devices found on platform {}, install the GPU driver",
+                platform.name().unwrap_or_default()
+            ),
+            1 => format!("Device index {device_idx} out of range, found 1 device: index 0"),
+            found => format!(
+                "Device index {device_idx} out of range, found {found} devices: indices 0 to {}",
+                found - 1
+            ),
+        };
+        ocl::Error::from(format!(
+            "{message}, run the devices subcommand to list them"
+        ))
+    })
+}
+
+/// Prints what's needed to make sense of a search that finds nothing or runs
+/// slowly: the platform and every device the search runs on, the work sizes
+/// it uses, the build log of the kernel and the condition it checks. Every
+/// device builds the kernel once more for its log, which doesn't affect the
+/// search itself.
+fn print_diagnostics(
+    term: &Term,
+    config: &Config,
+    platform: Platform,
+    kernel_src: &str,
+) -> ocl::Result<()> {
+    term.write_line(&format!(
+        "platform: {} ({}), {}",
+        platform.name()?,
+        platform.vendor()?,
+        platform.version()?
+    ))?;
+
+    for &device_idx in &config.gpu_devices {
+        let device = device_by_idx(platform, device_idx)?;
+        term.write_line(&format!(
+            "device {}: {} ({}), {}, driver {}",
+            device_idx,
+            device.name()?,
+            device.vendor()?,
+            device.info(DeviceInfo::Version)?,
+            device.info(DeviceInfo::DriverVersion)?
+        ))?;
+        term.write_line(&format!(
+            "  global work size: {:#x}, local work size: chosen by the driver, at most {}",
+            config.work_size,
+            device.max_wg_size()?
+        ))?;
+
+        let context = Context::builder()
+            .platform(platform)
+            .devices(device)
+            .build()?;
+        let program = Program::builder()
+            .devices(device)
+            .src(kernel_src)
+            .build(&context)?;
+        let log = match program.build_info(device, ProgramBuildInfo::BuildLog)? {
+            ProgramBuildInfoResult::BuildLog(log) => log,
+            other => other.to_string(),
+        };
+        if log.trim().is_empty() {
+            term.write_line("  build log: empty")?;
+        } else {
+            term.write_line(&format!("  build log:\n{}", log.trim_end()))?;
+        }
+    }
+
+    term.write_line(&format!(
+        "success condition: {} (SUCCESS_CONDITION() {})",
+        describe_conditions(config),
+        success_condition(config)
+    ))?;
+
+    Ok(())
+}
+
+/// Creates the OpenCL kernel source code by populating the template with the
+/// values from the Config object.
+pub fn mk_kernel_src(config: &Config) -> String {
+    mk_kernel_src_with_condition(config, &success_condition(config))
+}
+
+/// The kernel's predicates for the Config's thresholds, each with its bit of
+/// [`CONDITION_NAMES`].
+fn predicates(config: &Config) -> Vec<(usize, &'static str)> {
+    let prefix = if PrefixBloom::needed(&config.prefixes) {
+        "hasPrefixBloom(digest, prefixBloom)"
+    } else {
+        "hasPrefix(digest)"
+    };
+    [
+        (
+            config.leading_zeroes_threshold.is_some(),
+            "hasLeading(digest)",
+        ),
+        (config.total_zeroes_threshold.is_some(), "hasTotal(digest)"),
+        (
+            config.leading_zero_nibbles_threshold.is_some(),
+            "hasLeadingNibbles(digest)",
+        ),
+        (config.repeat_run.is_some(), "hasRepeatRun(digest)"),
+        (!config.prefixes.is_empty(), prefix),
+        (!config.match_bytes.is_empty(), "hasMatchBytes(digest)"),
+    ]
+    .into_iter()
+    .enumerate()
+    .filter(|(_, (set, _))| *set)
+    .map(|(bit, (_, predicate))| (bit, predicate))
+    .collect()
+}
+
+/// The kernel's `MATCHED_FLAGS`, setting the bit of every predicate that
+/// held for a hit.
+fn matched_flags(config: &Config) -> String {
+    let flags: Vec<String> = predicates(config)
+        .iter()
+        .map(|(bit, predicate)| format!("((ulong) ({predicate}) << {bit})"))
+        .collect();
+    if flags.is_empty() {
+        return "0".to_string();
+    }
+    flags.join(" | ")
+}
+
+/// The kernel's `SUCCESS_CONDITION`, derived from the Config's thresholds.
+fn success_condition(config: &Config) -> String {
+    let mut conditions: Vec<&str> = predicates(config)
+        .into_iter()
+        .map(|(_, predicate)| predicate)
+        .collect();
+    if conditions.is_empty() {
+        assert!(
+            config.checksum_regex.is_some()
+                || config.min_uppercase.is_some()
+                || config.address_range.is_some(),
+            "No threshold set."
+        );
+        // every address is left to the filters on the host
+        conditions.push("1");
+    }
+
+    let join = if config.require_all { " && " } else { " || " };
+    conditions.join(join)
+}
+
+/// Creates the OpenCL kernel source code like [`mk_kernel_src`] does, but with
+/// a custom success condition rather than the one derived from the Config.
+fn mk_kernel_src_with_condition(config: &Config, condition: &str) -> String {
+    let mut src = String::with_capacity(2048 + KERNEL_SRC.len());
+
+    let factory = config.factory.iter();
+    let prefix = salt_prefix(config);
+    for (i, x) in factory.chain(prefix.iter()).enumerate() {
+        writeln!(src, "#define S_{} {}u", i + 1, x).unwrap();
+    }
+
+    let hashes = &config.init_code_hashes;
+    writeln!(src, "#define NUM_HASHES {}u", hashes.len()).unwrap();
+    writeln!(src, "__constant uchar initCodeHashes[NUM_HASHES][32] = {{").unwrap();
+    for hash in hashes {
+        let bytes: Vec<String> = hash.iter().map(|x| format!("{x}u")).collect();
+        writeln!(src, "  {{ {} }},", bytes.join(", ")).unwrap();
+    }
+    writeln!(src, "}};").unwrap();
+    writeln!(src, "#define SALT_BYTES {}", config.salt_bytes).unwrap();
+
+    let lz = config.leading_zeroes_threshold.unwrap_or(0);
+    writeln!(src, "#define LEADING_ZEROES {lz}").unwrap();
+    let tz = config.total_zeroes_threshold.unwrap_or(0);
+    writeln!(src, "#define TOTAL_ZEROES {tz}").unwrap();
+    let ln = config.leading_zero_nibbles_threshold.unwrap_or(0);
+    writeln!(src, "#define LEADING_NIBBLES {ln}").unwrap();
+    let run = config.repeat_run.unwrap_or(0);
+    writeln!(src, "#define REPEAT_RUN {run}").unwrap();
+
+    // the few fixed bytes are compared directly
+    if !config.match_bytes.is_empty() {
+        let comparisons: Vec<String> = config
+            .match_bytes
+            .iter()
+            .map(|byte| format!("d[{}] == {}u", byte.position, byte.value))
+            .collect();
+        writeln!(
+            src,
+            "#define hasMatchBytes(d) ({})",
+            comparisons.join(" && ")
+        )
+        .unwrap();
+    }
+
+    // too many prefixes for the tables are checked against a Bloom filter
+    // passed to the kernel in a buffer
+    let bloom = PrefixBloom::new(&config.prefixes);
+    writeln!(src, "#define PREFIX_BLOOM {}", bloom.is_some() as u8).unwrap();
+    if let Some(bloom) = &bloom {
+        let lengths: Vec<String> = bloom.key_nibbles.iter().map(|x| format!("{x}u")).collect();
+        writeln!(src, "#define NUM_BLOOM_KEYS {}u", lengths.len()).unwrap();
+        writeln!(
+            src,
+            "__constant uint bloomKeyNibbles[NUM_BLOOM_KEYS] = {{ {} }};",
+            lengths.join(", ")
+        )
+        .unwrap();
+        writeln!(src, "#define BLOOM_HASHES {}u", prefix::BLOOM_HASHES).unwrap();
+        writeln!(src, "#define BLOOM_MASK {}u", bloom.mask()).unwrap();
+    }
+
+    // the prefixes are padded to the longest one, with empty masks past
+    // their end
+    let prefixes: &[AddressPrefix] = match bloom {
+        Some(_) => &[],
+        None => &config.prefixes,
+    };
+    let prefix_bytes = prefixes.iter().map(AddressPrefix::byte_len).max();
+    writeln!(src, "#define NUM_PREFIXES {}u", prefixes.len()).unwrap();
+    if let Some(prefix_bytes) = prefix_bytes {
+        writeln!(src, "#define PREFIX_BYTES {prefix_bytes}u").unwrap();
+        let (masks, targets): (Vec<_>, Vec<_>) = prefixes
+            .iter()
+            .map(|prefix| prefix.mask_and_target(prefix_bytes))
+            .unzip();
+        for (name, rows) in [("prefixMasks", masks), ("prefixTargets", targets)] {
+            writeln!(
+                src,
+                "__constant uchar {name}[NUM_PREFIXES][PREFIX_BYTES] = {{"
+            )
+            .unwrap();
+            for row in rows {
+                let bytes: Vec<String> = row.iter().map(|x| format!("{x}u")).collect();
+                writeln!(src, "  {{ {} }},", bytes.join(", ")).unwrap();
+            }
+            writeln!(src, "}};").unwrap();
+        }
+    }
+
+    writeln!(src, "#define SUCCESS_CONDITION() {}", condition).unwrap();
+    writeln!(src, "#define MATCHED_FLAGS() ({})", matched_flags(config)).unwrap();
+
+    let create2_mode = config.mode == Mode::Create2;
+    writeln!(src, "#define CREATE2_MODE {}", create2_mode as u8).unwrap();
+    writeln!(
+        src,
+        "#define CREATE3_VARIANT {}",
+        config.create3_variant as u8
+    )
+    .unwrap();
+    let nonces: Vec<String> = proxy_nonces(config)
+        .iter()
+        .map(|nonce| format!("{nonce}u"))
+        .collect();
+    writeln!(src, "#define NUM_NONCES {}u", nonces.len()).unwrap();
+    writeln!(
+        src,
+        "__constant uchar create1Nonces[NUM_NONCES] = {{ {} }};",
+        nonces.join(", ")
+    )
+    .unwrap();
+
+    writeln!(src, "#define MAX_SOLUTIONS {MAX_SOLUTIONS}u").unwrap();
+
+    src.push_str(KERNEL_SRC);
+
+    src
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::address;
+
+    fn builder() -> ConfigBuilder {
+        Config::builder()
+            .factory(Address::repeat_byte(0x11))
+            .owner(Address::repeat_byte(0x22))
+            .init_code_hash(CREATE3_PROXY_HASH)
+            .gpu_devices(vec![0])
+    }
+
+    /// The search the address vectors below were computed for independently.
+    fn vector_config() -> Config {
+        Config::builder()
+            .factory(address!("0000000000FFe8B47B3e2130213B802212439497"))
+            .owner(address!("000000000000000000000000000000000000dEaD"))
+            .init_code_hash(CREATE3_PROXY_HASH)
+            .gpu_devices(vec![0])
+            .leading_zeroes_threshold(1)
+            .build()
+            .unwrap()
+    }
+
+    const VECTOR_SALT: FixedBytes<8> = FixedBytes([1, 2, 3, 4, 0, 0, 0, 0]);
+    const VECTOR_NONCE: u64 = 0x1122334455667788;
+
+    #[test]
+    fn attempt_rate_is_per_interval() {
+        assert_eq!(attempt_rate(300, Duration::from_secs(2)), 150.0);
+        assert_eq!(attempt_rate(50, Duration::from_millis(500)), 100.0);
+        assert_eq!(attempt_rate(0, Duration::from_secs(1)), 0.0);
+        assert_eq!(attempt_rate(1_000, Duration::ZERO), 0.0);
+    }
+
+    #[test]
+    fn success_condition_joins_with_require_all() {
+        let config = builder()
+            .leading_zeroes_threshold(2)
+            .total_zeroes_threshold(4)
+            .build()
+            .unwrap();
+        assert!(mk_kernel_src(&config)
+            .contains("#define SUCCESS_CONDITION() hasLeading(digest) || hasTotal(digest)\n"));
+
+        let config = builder()
+            .leading_zeroes_threshold(2)
+            .total_zeroes_threshold(4)
+            .match_bytes(vec!["19=0x42".parse().unwrap()])
+            .require_all(true)
+            .build()
+            .unwrap();
+        assert!(mk_kernel_src(&config).contains(
+            "#define SUCCESS_CONDITION() hasLeading(digest) && hasTotal(digest) && hasMatchBytes(digest)\n"
+        ));
+    }
+
+    #[test]
+    fn kernel_flags_the_conditions_met() {
+        let config = builder()
+            .total_zeroes_threshold(2)
+            .prefixes(vec!["0xdead".parse().unwrap(), "0xde".parse().unwrap()])
+            .build()
+            .unwrap();
+        assert!(mk_kernel_src(&config).contains(
+            "#define MATCHED_FLAGS() (((ulong) (hasTotal(digest)) << 1) | ((ulong) (hasPrefix(digest)) << 4))\n"
+        ));
+
+        let address = address!("dead000000000000000000000000000000000001");
+        let flags = condition_flags(&config, &address);
+        assert_eq!(flags, 1 << 1 | 1 << 4);
+        assert_eq!(
+            matched_conditions(&config, &address, flags),
+            ["total_zeros", "prefix:0", "prefix:1"]
+        );
+        // only what the kernel flagged is reported
+        assert_eq!(
+            matched_conditions(&config, &address, 1 << 1),
+            ["total_zeros"]
+        );
+    }
+
+    #[test]
+    fn solutions_report_the_create1_nonce_minus_one() {
+        let config = vector_config();
+        let solution = mk_solution(&config, &Reward::new(), &VECTOR_SALT, VECTOR_NONCE, 1, 0, 0);
+        assert_eq!(solution.nonce, 0);
+        assert_eq!(
+            solution.address,
+            address!("357FC0E1C0aD569B3aabd4458AbAEDD3981d84ce")
+        );
+        assert_eq!(
+            solution.proxy_address,
+            address!("73f102fa001b366b719378ff2a9024d20b27F4E2")
+        );
+
+        let solution = mk_solution(&config, &Reward::new(), &VECTOR_SALT, VECTOR_NONCE, 2, 0, 0);
+        assert_eq!(solution.nonce, 1);
+        assert_eq!(
+            solution.address,
+            address!("77ED48Db89AE987FfEB56ABb5ac4d7B685A212e3")
+        );
+    }
+
+    #[test]
+    fn describe_nonces_lists_explicit_nonces() {
+        let mut config = vector_config();
+        config.max_create3_nonce = 3;
+        assert!(describe_nonces(&config)
+            .unwrap()
+            .ends_with("minus one: 0..=2"));
+
+        config.proxy_nonces = Some(vec![5]);
+        let description = describe_nonces(&config).unwrap();
+        assert!(description.contains("nonces 5, solutions report"));
+        assert!(description.ends_with("minus one: 4"));
+
+        config.proxy_nonces = Some(vec![1, 5]);
+        assert!(describe_nonces(&config)
+            .unwrap()
+            .ends_with("minus one: 0, 4"));
+    }
+
+    #[test]
+    fn reconstruct_address_lays_out_the_salt() {
+        let mut config = vector_config();
+        let (address, create2_salt) = reconstruct_address(
+            &config,
+            &VECTOR_SALT,
+            VECTOR_NONCE,
+            &CREATE3_PROXY_HASH,
+            127,
+        );
+        assert_eq!(
+            create2_salt,
+            b256!("000000000000000000000000000000000000dead010203048877665544332211")
+        );
+        assert_eq!(
+            address,
+            address!("d46DeE9cbf731bFF1E5368d8B1d85FD92847b1b2")
+        );
+
+        // more random salt bytes leave less of the nonce in the salt
+        config.salt_bytes = 8;
+        let salt = FixedBytes([1, 2, 3, 4, 5, 6, 7, 8]);
+        let (address, create2_salt) =
+            reconstruct_address(&config, &salt, VECTOR_NONCE, &CREATE3_PROXY_HASH, 127);
+        assert_eq!(
+            create2_salt,
+            b256!("000000000000000000000000000000000000dead010203040506070888776655")
+        );
+        assert_eq!(
+            address,
+            address!("75577522eA7fEAcF39476F619F8F6D16F8E71acE")
+        );
+
+        // in create2 mode the factory deploys the contract itself
+        config.salt_bytes = 4;
+        config.mode = Mode::Create2;
+        let (address, _) =
+            reconstruct_address(&config, &VECTOR_SALT, VECTOR_NONCE, &CREATE3_PROXY_HASH, 1);
+        assert_eq!(
+            address,
+            address!("73f102fa001b366b719378ff2a9024d20b27F4E2")
+        );
+    }
+
+    #[test]
+    fn create3_variants_hash_the_owner_into_the_salt() {
+        let mut config = vector_config();
+        config.create3_variant = Create3Variant::Solmate;
+        let (address, create2_salt) =
+            reconstruct_address(&config, &VECTOR_SALT, VECTOR_NONCE, &CREATE3_PROXY_HASH, 1);
+        assert_eq!(
+            factory_salt(&config, &create2_salt),
+            b256!("83ecd4180d978e6a59abaed61f011a936d9adda6685ed174141a380507ac07b8")
+        );
+        assert_eq!(
+            address,
+            address!("8Cc5eBDFED3c9c54380F6Ff776b30E507879058d")
+        );
+
+        // createx needs the byte after the owner to be 0
+        config.create3_variant = Create3Variant::CreateX;
+        let (address, create2_salt) =
+            reconstruct_address(&config, &VECTOR_SALT, VECTOR_NONCE, &CREATE3_PROXY_HASH, 1);
+        assert_eq!(
+            create2_salt,
+            b256!("000000000000000000000000000000000000dead000203048877665544332211")
+        );
+        assert_eq!(
+            factory_salt(&config, &create2_salt),
+            b256!("f7e8bb297035e2944b04fa730d52543dde6798bf27efec7310b96271e260bd89")
+        );
+        assert_eq!(
+            address,
+            address!("07DaC67B69665aE0011dC8DF907d14Cb8083a073")
+        );
+    }
+
+    /// Run with `cargo test -- --ignored` where an OpenCL platform is
+    /// installed, e.g. POCL on a machine without a GPU.
+    #[test]
+    #[ignore = "needs an OpenCL platform, e.g. POCL"]
+    fn self_test_passes_for_every_create3_variant() {
+        let mut config = builder()
+            .leading_zeroes_threshold(1)
+            .allow_small_work_size(true)
+            .work_size(0x1000)
+            .build()
+            .unwrap();
+        self_test(&mut config, default_platform().unwrap(), 0).unwrap();
+    }
+
+    #[test]
+    fn next_nonce_stops_at_the_nonce_limit() {
+        let mut config = vector_config();
+        assert_eq!(nonce_limit(4), u32::MAX as u64 + 1);
+        assert_eq!(next_nonce(&config, u32::MAX - 1), Some(u32::MAX));
+        // the last nonce of 4 salt bytes ends the salt rather than wrapping
+        assert_eq!(next_nonce(&config, u32::MAX), None);
+
+        config.salt_bytes = 5;
+        let limit = nonce_limit(5) as u32;
+        assert_eq!(limit, 1 << 24);
+        assert_eq!(next_nonce(&config, limit - 2), Some(limit - 1));
+        assert_eq!(next_nonce(&config, limit - 1), None);
+        // resumed from a search with fewer salt bytes
+        assert_eq!(next_nonce(&config, limit), None);
+        assert_eq!(next_nonce(&config, u32::MAX), None);
+
+        config.salt_bytes = 8;
+        assert_eq!(nonce_limit(8), 1);
+        assert_eq!(next_nonce(&config, 0), None);
+
+        // a sequential search moves on from the last salt to the first
+        config.salt_mode = SaltMode::Sequential;
+        let last = SearchState::sequential(u64::MAX, 8);
+        let next = next_state(&config, &last, 1).unwrap();
+        assert_eq!(next.counter(8), 0);
+        assert_eq!(next.nonce, 0);
+    }
 }
@@ -1,34 +1,258 @@
-use alloy_primitives::{Address, FixedBytes};
-use clap::Parser;
+use alloy_primitives::{hex, Address, Bytes, FixedBytes};
+use clap::{Parser, Subcommand};
 use clap_num::maybe_hex;
-use create3crunch::{gpu, Config};
+use create3crunch::{
+    check_kernels, cpu, default_summary_file, gpu, list_devices, mk_kernel_src, once, verify,
+    AddressPrefix, AddressRange, Config, Create3Variant, MatchByte, Mode, NonceEndian,
+    NotifyOverflow, OutputFormat, OutputTemplate, SaltMode,
+};
+use regex::Regex;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing_subscriber::EnvFilter;
 
-fn parse_worksize(s: &str) -> Result<u32, String> {
-    let work_size = maybe_hex::<u32>(s)?;
-    if work_size < 0x1540000 {
-        return Err("Work size cannot be below 0x15400000".to_string());
+fn parse_hex(s: &str) -> Result<Bytes, String> {
+    hex::decode(s)
+        .map(Bytes::from)
+        .map_err(|err| err.to_string())
+}
+
+/// How the diagnostic events filtered through `RUST_LOG` are printed.
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+enum LogFormat {
+    /// Human readable lines.
+    #[default]
+    Text,
+    /// One JSON object per event.
+    Json,
+}
+
+/// Settings loaded from a `--config` TOML file, using the same names as the
+/// fields of [`Config`]. Flags passed on the command line take precedence.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    factory: Option<Address>,
+    owner: Option<Address>,
+    init_code_hashes: Option<Vec<FixedBytes<32>>>,
+    init_codes: Option<Vec<Bytes>>,
+    work_size: Option<u32>,
+    gpu_devices: Option<Vec<u8>>,
+    max_create3_nonce: Option<u8>,
+    proxy_nonces: Option<Vec<u8>>,
+    leading_zeroes_threshold: Option<u8>,
+    total_zeroes_threshold: Option<u8>,
+    leading_zero_nibbles_threshold: Option<u8>,
+    repeat_run: Option<u8>,
+    prefix_list: Option<String>,
+    match_bytes: Option<Vec<MatchByte>>,
+    mimic: Option<Address>,
+    mimic_nibbles: Option<u8>,
+    checksum_regex: Option<String>,
+    min_uppercase: Option<u8>,
+    address_range: Option<AddressRange>,
+    output_file: Option<String>,
+    output_format: Option<OutputFormat>,
+    output_template: Option<OutputTemplate>,
+    tag: Option<String>,
+    durable: Option<bool>,
+    state_file: Option<String>,
+    reward_file: Option<String>,
+    quiet: Option<bool>,
+    nonce_endian: Option<NonceEndian>,
+    verbose: Option<bool>,
+    max_results: Option<usize>,
+    min_reward_score: Option<f64>,
+    stop_at_reward: Option<String>,
+    timeout: Option<u64>,
+    summary_file: Option<String>,
+    stats_file: Option<String>,
+    stats_interval: Option<u64>,
+    throttle: Option<u8>,
+    seed: Option<u64>,
+    mode: Option<Mode>,
+    create3_variant: Option<Create3Variant>,
+    salt_prefix: Option<Bytes>,
+    free_salt: Option<bool>,
+    salt_bytes: Option<u8>,
+    salt_mode: Option<SaltMode>,
+    salt_start: Option<u64>,
+    salt: Option<Bytes>,
+    resume_nonce: Option<u32>,
+    nonce_stride: Option<u32>,
+    metrics_addr: Option<SocketAddr>,
+    ws_addr: Option<SocketAddr>,
+    require_all: Option<bool>,
+    auto_clamp: Option<bool>,
+    allow_small_work_size: Option<bool>,
+    autotune: Option<bool>,
+    max_compute_units: Option<u32>,
+    self_test: Option<bool>,
+    notify_threshold: Option<u8>,
+    notify_cmd: Option<String>,
+    notify_concurrency: Option<u8>,
+    notify_overflow: Option<NotifyOverflow>,
+}
+
+impl FileConfig {
+    fn load(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| format!("Couldn't read config file `{path}`: {err}"))?;
+        toml::from_str(&contents).map_err(|err| format!("Invalid config file `{path}`: {err}"))
     }
-    Ok(work_size)
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the available OpenCL devices and the indices to select them by
+    Devices,
+    /// Recompute the address of every solution in an output file from the
+    /// factory, owner and initcode hashes given before the subcommand, reporting
+    /// the lines that don't deploy to the recorded address
+    Verify {
+        #[arg(long, help = "The file to verify [default: the output file]")]
+        input: Option<String>,
+    },
+    /// Print how many addresses have to be checked per solution meeting the
+    /// thresholds given before the subcommand, and how long that takes
+    Difficulty {
+        #[arg(
+            long,
+            value_name = "MH/S",
+            help = "The hash rate in million addresses per second to estimate the time at [default: measured on the given GPU devices]"
+        )]
+        rate: Option<f64>,
+
+        #[arg(
+            long,
+            value_name = "SECONDS",
+            default_value = "10",
+            help = "How many seconds to measure the hash rate for without --rate"
+        )]
+        measure: u64,
+    },
+    /// Build the kernel for the thresholds given before the subcommand and for
+    /// a range of representative conditions and modes on the given devices,
+    /// without running it. A software OpenCL platform like POCL can check the
+    /// generated source on machines without a GPU, select it with
+    /// OCL_DEFAULT_PLATFORM_IDX
+    CheckKernels,
 }
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Args {
-    #[arg(short, long, help = "Address of the CREATE3 factory contract")]
-    factory: Address,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[arg(
+        long,
+        env = "CREATE3_CONFIG",
+        help = "TOML file to load the configuration from; flags take precedence over their CREATE3_* environment variables, which take precedence over the file"
+    )]
+    config: Option<String>,
+
+    #[arg(
+        short,
+        long,
+        env = "CREATE3_FACTORY",
+        help = "Address of the CREATE3 factory contract"
+    )]
+    factory: Option<Address>,
+
+    #[arg(
+        short,
+        long,
+        env = "CREATE3_OWNER",
+        help = "Owner / Caller address (first 20-bytes of the top-level salt will be set to the address unless --salt-prefix is given)"
+    )]
+    owner: Option<Address>,
+
+    #[arg(
+        long,
+        env = "CREATE3_SALT_PREFIX",
+        value_parser = parse_hex,
+        help = "Up to 20 bytes of hex to start the salt with instead of the owner address, only for factories that don't bind salts to the caller"
+    )]
+    salt_prefix: Option<Bytes>,
+
+    #[arg(
+        long,
+        conflicts_with = "salt_prefix",
+        help = "Start the salt with 20 random bytes instead of the owner address, only for factories that don't bind salts to the caller; anyone can deploy with the found salts"
+    )]
+    free_salt: bool,
+
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u8).range(4..=8),
+        help = "How many bytes of the salt after the owner or prefix are random per salt, the rest is enumerated [default: 4]"
+    )]
+    salt_bytes: Option<u8>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Whether salts are drawn at random or counted up from --salt-start, so the searched space is contiguous and reproducible [default: random]"
+    )]
+    salt_mode: Option<SaltMode>,
+
+    #[arg(
+        long,
+        help = "The salt a sequential search starts at, as a number, give every machine a disjoint range to split a search between them [default: 0]"
+    )]
+    salt_start: Option<u64>,
+
+    #[arg(
+        long,
+        value_parser = parse_hex,
+        requires = "resume_nonce",
+        help = "The random part of the salt to start the search at, exactly --salt-bytes bytes of hex; with --resume-nonce, pins the start of a single-device search to split it between machines by hand"
+    )]
+    salt: Option<Bytes>,
+
+    #[arg(
+        long,
+        requires = "salt",
+        help = "The nonce to start searching --salt at"
+    )]
+    resume_nonce: Option<u32>,
+
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Move on to a fresh random salt after searching this many batches of its nonces, trading kernel launches per salt for more distinct salts [default: until a hit or the salt's nonces run out]"
+    )]
+    nonce_stride: Option<u32>,
 
     #[arg(
         short,
         long,
-        help = "Owner / Caller address (first 20-bytes of the top-level salt will be set to the address)"
+        env = "CREATE3_INITCODE_HASH",
+        value_delimiter = ',',
+        help = "Hash(es) of the factory's deploy proxy initcode, comma separated to search several at once"
     )]
-    owner: Address,
+    initcode_hash: Vec<FixedBytes<32>>,
 
-    #[arg(short, long, help = "Hash of the factory's deploy proxy initcode")]
-    initcode_hash: FixedBytes<32>,
+    #[arg(
+        long,
+        value_parser = parse_hex,
+        value_delimiter = ',',
+        help = "The deploy proxy's initcode itself, hashed on startup in place of --initcode-hash; if both are given, the hashes have to match"
+    )]
+    initcode: Vec<Bytes>,
 
-    #[arg(short, long, help = "GPU Device")]
-    gpu_device: u8,
+    #[arg(
+        short,
+        long,
+        env = "CREATE3_GPU_DEVICE",
+        value_delimiter = ',',
+        help = "GPU Device(s), comma separated to mine on several devices at once"
+    )]
+    gpu_device: Vec<u8>,
 
     #[arg(
         short,
@@ -44,49 +268,713 @@ struct Args {
     )]
     total_zeros: Option<u8>,
 
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u8).range(1..=40),
+        visible_alias = "leading-checksum-zeros",
+        help = "Minimum amount of leading zero nibbles (half-bytes) for the address to be considered valuable, the same as the leading 0s of the checksummed address"
+    )]
+    leading_zero_nibbles: Option<u8>,
+
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u8).range(1..=40),
+        help = "Minimum amount of consecutive identical nibbles anywhere in the address for it to be considered valuable"
+    )]
+    repeat_run: Option<u8>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "File of hex address prefixes, one per line, of which the address has to start with any one; checked on the GPU one after the other, so keep the list to the prefixes you want; lists of thousands are checked against a Bloom filter of up to 8 leading nibbles, keyed by each prefix's own length, with the full prefixes checked on the host"
+    )]
+    prefix_list: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "POS=HEX",
+        help = "Fix the address byte at POS (0 to 19) to HEX, leaving the other bytes free; repeat to fix several, which all have to match"
+    )]
+    match_byte: Vec<MatchByte>,
+
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        help = "Search for addresses looking like ADDRESS, sharing its first and last --mimic-nibbles hex digits, which are added as match bytes"
+    )]
+    mimic: Option<Address>,
+
+    #[arg(
+        long,
+        requires = "mimic",
+        help = "How many hex digits at each end of the --mimic address to match, an even number between 2 and 20 [default: 4]"
+    )]
+    mimic_nibbles: Option<u8>,
+
+    #[arg(
+        long,
+        value_name = "RE",
+        help = "Regex the checksummed address has to match, checked on the host after the thresholds; without a threshold the GPU can only report a few candidates per batch, so pair it with one"
+    )]
+    checksum_regex: Option<String>,
+
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u8).range(1..=40),
+        help = "Minimum amount of uppercase letters in the checksummed address, checked on the host like --checksum-regex"
+    )]
+    min_uppercase: Option<u8>,
+
+    #[arg(
+        long,
+        visible_alias = "addr-range",
+        value_name = "LO..HI",
+        help = "Inclusive range in hex the address has to lie in as an integer, e.g. 0x1..0xffffffffff, checked on the host like --checksum-regex; without a threshold, its leading zero nibbles are used as one"
+    )]
+    address_range: Option<AddressRange>,
+
+    #[arg(
+        long,
+        help = "Only accept addresses meeting every given threshold, by default meeting any one of them is enough"
+    )]
+    require_all: bool,
+
     #[arg(
         short,
         long,
-        help = "Specifies the upper bound for the nonces that will be inclusively checked (1 - n), at most 127",
-        default_value_t = 32
+        help = "Specifies the upper bound for the nonces that will be inclusively checked (1 - n), at most 127 [default: 32]"
     )]
-    max_create3_nonce: u8,
+    max_create3_nonce: Option<u8>,
 
-    #[arg(short, long, value_parser=parse_worksize, default_value_t=0x4000000, help="Specifies the GPU work size, min. 0x154000")]
-    work_size: u32,
+    #[arg(
+        long = "nonces",
+        value_delimiter = ',',
+        conflicts_with = "max_create3_nonce",
+        value_parser = clap::value_parser!(u8).range(1..=127),
+        help = "Comma-separated create1 nonces of the deploy proxy to search instead of 1 up to --max-create3-nonce, each between 1 and 127, e.g. 1,5,17"
+    )]
+    proxy_nonces: Vec<u8>,
+
+    #[arg(short, long, value_parser=maybe_hex::<u32>, help="Specifies the GPU work size, min. 0x1540000 [default: 0x4000000]")]
+    work_size: Option<u32>,
+
+    /// Accept work sizes down to 0x100 for quick iterations while developing
+    #[arg(long, hide = true)]
+    allow_small_work_size: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["work_size", "cpu", "benchmark", "once"],
+        help = "Measure a range of work sizes for a few seconds each before mining and use the fastest"
+    )]
+    autotune: bool,
+
+    #[arg(
+        long,
+        help = "Round the work size down to fit the devices instead of exiting when it doesn't"
+    )]
+    auto_clamp: bool,
+
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Run the kernel on at most this many of each device's compute units at a time, so a GPU shared with a display doesn't stutter; clamped to the compute units the device has, each batch is split into launches of this many work groups"
+    )]
+    max_compute_units: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Check each device's keccak implementation against the CPU before mining on it"
+    )]
+    self_test: bool,
 
     #[arg(
         short = 'p',
         long,
-        default_value = "efficient_addresses.txt",
-        help = "The file to output efficient addresses to"
+        help = "The file to output efficient addresses to, `-` for stdout [default: efficient_addresses.txt]"
+    )]
+    output_file: Option<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "The format solutions are written to the output file in [default: text]"
+    )]
+    output_format: Option<OutputFormat>,
+
+    #[arg(
+        long,
+        help = "Line to write solutions as in the text format, with the placeholders {address} (or {deployed_address}), {address_value}, {proxy_address}, {salt}, {nonce}, {total_zeros}, {leading_zeros}, {leading_zero_nibbles}, {uppercase}, {reward}, {init_code_hash}, {matched}, {id} and {timestamp} [default: \"{salt} ({nonce}) => {address} => {reward} (proxy {proxy_address})\"]"
+    )]
+    output_template: Option<OutputTemplate>,
+
+    #[arg(
+        long,
+        help = "Tag written solutions with an id of TAG:RUN:SEQ, RUN being random per run and SEQ counting its solutions, to trace them in output files merged from many miners"
+    )]
+    tag: Option<String>,
+
+    #[arg(
+        long,
+        help = "Sync the output file to disk after every solution so none are lost on a crash or power loss, at the cost of speed when solutions are frequent"
+    )]
+    durable: bool,
+
+    #[arg(
+        long,
+        help = "File to periodically save the search state to, resuming from it on startup"
+    )]
+    state_file: Option<String>,
+
+    #[arg(
+        long,
+        help = "TOML file mapping zero counts to rewards, overriding the built-in table"
+    )]
+    reward_file: Option<String>,
+
+    #[arg(
+        short,
+        long,
+        help = "Print plain status lines instead of the dashboard (implied when stdout isn't a terminal)"
+    )]
+    quiet: bool,
+
+    #[arg(
+        long,
+        help = "Print the platform, devices, driver and OpenCL versions, work sizes, kernel build log and success condition before mining, to include in bug reports; implies --quiet so they stay on screen"
+    )]
+    verbose: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Byte order of the nonce in the dashboard's search space lines, which show the salt bytes, xxxxxxxx for the work item index, then the nonce bytes that fit: le as they appear in the salt, be as the number reads [default: le]"
+    )]
+    nonce_endian: Option<NonceEndian>,
+
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Exit after writing this many solutions instead of running until stopped"
     )]
-    output_file: String,
+    max_results: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Only record solutions whose reward is at least this, to run broad searches but keep only the rare results"
+    )]
+    min_reward_score: Option<f64>,
+
+    #[arg(
+        long,
+        value_name = "REWARD",
+        help = "Stop once a solution is worth at least this reward, a number or a reward of the table such as a name from --reward-file, which then stands for its least rare zero counts"
+    )]
+    stop_at_reward: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Exit after searching for this many seconds, whether or not anything was found"
+    )]
+    timeout: Option<u64>,
+
+    #[arg(
+        long,
+        help = "File to append a line of JSON summarizing the run to once it stops [default: run_summary.jsonl next to the output file]"
+    )]
+    summary_file: Option<String>,
+
+    #[arg(
+        long,
+        help = "JSON file to keep the rate, solutions found, cumulative nonce and uptime of the running search in, replaced atomically on every update"
+    )]
+    stats_file: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "How often to update the stats file [default: 1]"
+    )]
+    stats_interval: Option<u64>,
+
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u8).range(0..=100),
+        help = "Percentage of each work cycle to sleep for to conserve CPU, 0 maximizes the hash rate at the cost of a busy host thread [default: 98]"
+    )]
+    throttle: Option<u8>,
+
+    #[arg(
+        long,
+        help = "Seed for the random salts and nonces, searching the same salts on every run"
+    )]
+    seed: Option<u64>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Whether the factory deploys through a CREATE3 proxy or directly through CREATE2, --max-create3-nonce is ignored for the latter [default: create3]"
+    )]
+    mode: Option<Mode>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "How the CREATE3 factory derives the deploy proxy's salt, solmate and createx default the initcode hash to their proxy's [default: default]"
+    )]
+    create3_variant: Option<Create3Variant>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value = "text",
+        help = "The format of the diagnostics printed to stderr, filtered through RUST_LOG"
+    )]
+    log_format: LogFormat,
+
+    #[arg(
+        long,
+        help = "Ring the terminal bell when a solution with at least this many total zero bytes is found"
+    )]
+    notify_threshold: Option<u8>,
+
+    #[arg(
+        long,
+        requires = "notify_threshold",
+        help = "Command to also run for such solutions, getting the address and reward as arguments and CREATE3CRUNCH_{ADDRESS,REWARD,SALT} in its environment"
+    )]
+    notify_cmd: Option<String>,
+
+    #[arg(
+        long,
+        requires = "notify_cmd",
+        value_parser = clap::value_parser!(u8).range(1..),
+        help = "How many notification commands run at once [default: 2]"
+    )]
+    notify_concurrency: Option<u8>,
+
+    #[arg(
+        long,
+        value_enum,
+        requires = "notify_cmd",
+        help = "Whether to drop notification commands or hold up the search while as many are queued as are running [default: drop]"
+    )]
+    notify_overflow: Option<NotifyOverflow>,
+
+    #[arg(
+        long,
+        help = "Address to serve Prometheus metrics on while mining, e.g. 127.0.0.1:9090"
+    )]
+    metrics_addr: Option<SocketAddr>,
+
+    #[arg(
+        long,
+        help = "Address to serve a WebSocket on that sends every written solution to its clients as JSON, e.g. 127.0.0.1:9091; slow clients are disconnected"
+    )]
+    ws_addr: Option<SocketAddr>,
+
+    #[arg(
+        long,
+        help = "Mine on the CPU instead of through OpenCL (slow, meant for testing)"
+    )]
+    cpu: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "cpu",
+        help = "Measure the hash rate for --duration seconds and exit, ignoring the thresholds"
+    )]
+    benchmark: bool,
+
+    #[arg(
+        long,
+        requires = "benchmark",
+        default_value = "10",
+        help = "How many seconds to run the benchmark for"
+    )]
+    duration: u64,
+
+    #[arg(
+        long,
+        requires = "benchmark",
+        help = "Print the benchmark result as JSON"
+    )]
+    json: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["benchmark", "cpu"],
+        help = "Print the generated OpenCL kernel source and exit without mining"
+    )]
+    dry_run: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["benchmark", "cpu", "dry_run"],
+        help = "Search a single work batch at a random salt on the first device and exit"
+    )]
+    once: bool,
 }
 
 impl TryInto<Config> for Args {
     type Error = String;
 
     fn try_into(self) -> Result<Config, Self::Error> {
-        if self.leading_zeros.is_none() && self.total_zeros.is_none() {
-            return Err("Must specify at least either the total zeros or leading zeros threshold, cannot leave both empty".to_string());
-        }
-        Ok(Config {
-            factory: self.factory,
-            owner: self.owner,
-            init_code_hash: self.initcode_hash,
-            work_size: self.work_size,
-            gpu_device: self.gpu_device,
-            leading_zeroes_threshold: self.leading_zeros,
-            total_zeroes_threshold: self.total_zeros,
-            max_create3_nonce: self.max_create3_nonce,
-            output_file: self.output_file,
-        })
+        let file = match &self.config {
+            Some(path) => FileConfig::load(path)?,
+            None => FileConfig::default(),
+        };
+        // verifying only reconstructs addresses on the host, and estimating
+        // the difficulty only needs a device to measure the rate on
+        let verifying = matches!(self.command, Some(Command::Verify { .. }));
+        let estimating = matches!(self.command, Some(Command::Difficulty { .. }));
+        let checking = matches!(self.command, Some(Command::CheckKernels));
+
+        let mut builder = Config::builder()
+            .allow_no_gpu_devices(self.cpu || self.dry_run || verifying || estimating)
+            .allow_no_threshold(self.benchmark || verifying || checking);
+
+        if let Some(factory) = self.factory.or(file.factory) {
+            builder = builder.factory(factory);
+        }
+        if let Some(owner) = self.owner.or(file.owner) {
+            builder = builder.owner(owner);
+        }
+        builder = builder.init_code_hashes(if self.initcode_hash.is_empty() {
+            file.init_code_hashes.unwrap_or_default()
+        } else {
+            self.initcode_hash
+        });
+        let init_codes = if self.initcode.is_empty() {
+            file.init_codes.unwrap_or_default()
+        } else {
+            self.initcode
+        };
+        for init_code in &init_codes {
+            builder = builder.init_code(init_code);
+        }
+        if let Some(work_size) = self.work_size.or(file.work_size) {
+            builder = builder.work_size(work_size);
+        }
+        builder = builder.gpu_devices(if self.gpu_device.is_empty() {
+            file.gpu_devices.unwrap_or_default()
+        } else {
+            self.gpu_device
+        });
+        if let Some(max_create3_nonce) = self.max_create3_nonce.or(file.max_create3_nonce) {
+            builder = builder.max_create3_nonce(max_create3_nonce);
+        }
+        let proxy_nonces = if self.proxy_nonces.is_empty() {
+            file.proxy_nonces
+        } else {
+            Some(self.proxy_nonces)
+        };
+        if let Some(proxy_nonces) = proxy_nonces {
+            builder = builder.proxy_nonces(proxy_nonces);
+        }
+
+        if let Some(threshold) = self.leading_zeros.or(file.leading_zeroes_threshold) {
+            builder = builder.leading_zeroes_threshold(threshold);
+        }
+        if let Some(threshold) = self.total_zeros.or(file.total_zeroes_threshold) {
+            builder = builder.total_zeroes_threshold(threshold);
+        }
+        if let Some(threshold) = self
+            .leading_zero_nibbles
+            .or(file.leading_zero_nibbles_threshold)
+        {
+            builder = builder.leading_zero_nibbles_threshold(threshold);
+        }
+        if let Some(repeat_run) = self.repeat_run.or(file.repeat_run) {
+            builder = builder.repeat_run(repeat_run);
+        }
+        if let Some(path) = self.prefix_list.or(file.prefix_list) {
+            builder = builder.prefixes(AddressPrefix::load(&path)?);
+        }
+        builder = builder.match_bytes(if self.match_byte.is_empty() {
+            file.match_bytes.unwrap_or_default()
+        } else {
+            self.match_byte
+        });
+        if let Some(address) = self.mimic.or(file.mimic) {
+            let nibbles = self.mimic_nibbles.or(file.mimic_nibbles).unwrap_or(4);
+            let mimicked = MatchByte::mimic(&address, nibbles)?;
+            let described: Vec<String> = mimicked.iter().map(MatchByte::to_string).collect();
+            eprintln!(
+                "Mimicking {address} with the match bytes {}",
+                described.join(" ")
+            );
+            builder = builder.mimic(address, nibbles);
+        }
+        if let Some(pattern) = self.checksum_regex.or(file.checksum_regex) {
+            let regex =
+                Regex::new(&pattern).map_err(|err| format!("Invalid checksum regex: {err}"))?;
+            builder = builder.checksum_regex(regex);
+        }
+        if let Some(min_uppercase) = self.min_uppercase.or(file.min_uppercase) {
+            builder = builder.min_uppercase(min_uppercase);
+        }
+        if let Some(address_range) = self.address_range.or(file.address_range) {
+            builder = builder.address_range(address_range);
+        }
+
+        let output_file = self
+            .output_file
+            .or(file.output_file)
+            .unwrap_or_else(|| "efficient_addresses.txt".to_string());
+        let summary_file = self
+            .summary_file
+            .or(file.summary_file)
+            .unwrap_or_else(|| default_summary_file(&output_file));
+        builder = builder
+            .output_file(output_file)
+            .summary_file(summary_file)
+            .output_format(
+                self.output_format
+                    .or(file.output_format)
+                    .unwrap_or_default(),
+            );
+        if let Some(output_template) = self.output_template.or(file.output_template) {
+            builder = builder.output_template(output_template);
+        }
+        if let Some(tag) = self.tag.or(file.tag) {
+            builder = builder.tag(tag);
+        }
+        if let Some(state_file) = self.state_file.or(file.state_file) {
+            builder = builder.state_file(state_file);
+        }
+        if let Some(reward_file) = self.reward_file.or(file.reward_file) {
+            builder = builder.reward_file(reward_file);
+        }
+        if let Some(max_results) = self.max_results {
+            builder = builder.max_results(max_results as usize);
+        } else if let Some(max_results) = file.max_results {
+            builder = builder.max_results(max_results);
+        }
+        if let Some(min_reward_score) = self.min_reward_score.or(file.min_reward_score) {
+            builder = builder.min_reward_score(min_reward_score);
+        }
+        if let Some(stop_at_reward) = self.stop_at_reward.or(file.stop_at_reward) {
+            builder = builder.stop_at_reward(stop_at_reward);
+        }
+        if let Some(timeout) = self.timeout.or(file.timeout) {
+            builder = builder.timeout(Duration::from_secs(timeout));
+        }
+        if let Some(stats_file) = self.stats_file.or(file.stats_file) {
+            builder = builder.stats_file(stats_file);
+        }
+        if let Some(stats_interval) = self.stats_interval.or(file.stats_interval) {
+            builder = builder.stats_interval(Duration::from_secs(stats_interval));
+        }
+        if let Some(throttle) = self.throttle.or(file.throttle) {
+            builder = builder.throttle(throttle);
+        }
+        if let Some(seed) = self.seed.or(file.seed) {
+            builder = builder.seed(seed);
+        }
+
+        builder = builder
+            .mode(self.mode.or(file.mode).unwrap_or_default())
+            .create3_variant(
+                self.create3_variant
+                    .or(file.create3_variant)
+                    .unwrap_or_default(),
+            );
+        if let Some(salt_prefix) = self.salt_prefix.or(file.salt_prefix) {
+            builder = builder.salt_prefix(salt_prefix);
+        }
+        if let Some(salt_bytes) = self.salt_bytes.or(file.salt_bytes) {
+            builder = builder.salt_bytes(salt_bytes);
+        }
+        builder = builder.salt_mode(self.salt_mode.or(file.salt_mode).unwrap_or_default());
+        if let Some(salt_start) = self.salt_start.or(file.salt_start) {
+            builder = builder.salt_start(salt_start);
+        }
+        match (
+            self.salt.or(file.salt),
+            self.resume_nonce.or(file.resume_nonce),
+        ) {
+            (Some(salt), Some(nonce)) => builder = builder.start(salt, nonce),
+            (None, None) => {}
+            _ => return Err("A start salt and nonce must be given together".to_string()),
+        }
+        if let Some(nonce_stride) = self.nonce_stride.or(file.nonce_stride) {
+            builder = builder.nonce_stride(nonce_stride);
+        }
+        if let Some(metrics_addr) = self.metrics_addr.or(file.metrics_addr) {
+            builder = builder.metrics_addr(metrics_addr);
+        }
+        if let Some(ws_addr) = self.ws_addr.or(file.ws_addr) {
+            builder = builder.ws_addr(ws_addr);
+        }
+        if let Some(max_compute_units) = self.max_compute_units.or(file.max_compute_units) {
+            builder = builder.max_compute_units(max_compute_units);
+        }
+        if let Some(notify_threshold) = self.notify_threshold.or(file.notify_threshold) {
+            builder = builder.notify_threshold(notify_threshold);
+        }
+        if let Some(notify_cmd) = self.notify_cmd.or(file.notify_cmd) {
+            builder = builder.notify_cmd(notify_cmd);
+        }
+        if let Some(notify_concurrency) = self.notify_concurrency.or(file.notify_concurrency) {
+            builder = builder.notify_concurrency(notify_concurrency);
+        }
+
+        builder
+            .free_salt(self.free_salt || file.free_salt.unwrap_or(false))
+            .durable(self.durable || file.durable.unwrap_or(false))
+            .quiet(self.quiet || file.quiet.unwrap_or(false))
+            .verbose(self.verbose || file.verbose.unwrap_or(false))
+            .nonce_endian(self.nonce_endian.or(file.nonce_endian).unwrap_or_default())
+            .require_all(self.require_all || file.require_all.unwrap_or(false))
+            .auto_clamp(self.auto_clamp || file.auto_clamp.unwrap_or(false))
+            .allow_small_work_size(
+                self.allow_small_work_size || file.allow_small_work_size.unwrap_or(false),
+            )
+            .autotune(self.autotune || file.autotune.unwrap_or(false))
+            .self_test(self.self_test || file.self_test.unwrap_or(false))
+            .notify_overflow(
+                self.notify_overflow
+                    .or(file.notify_overflow)
+                    .unwrap_or_default(),
+            )
+            .build()
+    }
+}
+
+/// Prints diagnostic events to stderr, only warnings unless `RUST_LOG` says
+/// otherwise, so they can be collected separately from the dashboard.
+fn init_tracing(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn"));
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    match format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber.json().init(),
     }
 }
 
 fn main() {
     let args = Args::parse();
+    init_tracing(args.log_format);
+
+    let checking = matches!(args.command, Some(Command::CheckKernels));
+    let (verify_input, difficulty_args) = match &args.command {
+        Some(Command::Devices) => {
+            list_devices().unwrap();
+            return;
+        }
+        Some(Command::Verify { input }) => (Some(input.clone()), None),
+        Some(Command::Difficulty { rate, measure }) => (None, Some((*rate, *measure))),
+        Some(Command::CheckKernels) | None => (None, None),
+    };
+    let use_cpu = args.cpu;
+    let dry_run = args.dry_run;
+    let search_once = args.once;
+    let benchmark = args
+        .benchmark
+        .then(|| (Duration::from_secs(args.duration), args.json));
+    let mut config: Config = args.try_into().unwrap();
+
+    if let Some(input) = verify_input {
+        let path = input.unwrap_or_else(|| config.output_file.clone());
+        let verification = verify(&config, &path).unwrap();
+        println!(
+            "{} verified, {} mismatched, {} skipped",
+            verification.verified, verification.mismatched, verification.skipped
+        );
+        if verification.mismatched != 0 {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some((rate, measure)) = difficulty_args {
+        print_difficulty(config, rate, Duration::from_secs(measure));
+        return;
+    }
+
+    if checking {
+        let failed = check_kernels(config).unwrap();
+        if failed != 0 {
+            println!("{failed} kernel builds failed");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if dry_run {
+        print!("{}", mk_kernel_src(&config));
+        return;
+    }
+
+    if search_once {
+        once(config).unwrap();
+        return;
+    }
+
+    // stop gracefully on Ctrl-C so the output file is flushed and unlocked
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let handler_flag = stop_flag.clone();
+    ctrlc::set_handler(move || handler_flag.store(true, Ordering::Relaxed))
+        .expect("Couldn't install Ctrl-C handler.");
+    config.stop_flag = Some(stop_flag);
+
+    if let Some((duration, json)) = benchmark {
+        let result = create3crunch::benchmark(config, duration).unwrap();
+        if json {
+            println!(
+                "{{\"hashes\":{},\"seconds\":{},\"hashes_per_second\":{}}}",
+                result.hashes,
+                result.seconds,
+                result.hashes_per_second()
+            );
+        } else {
+            println!(
+                "{} hashes in {:.2} seconds: {:.2} million hashes per second",
+                result.hashes,
+                result.seconds,
+                result.hashes_per_second() / 1_000_000.0
+            );
+        }
+    } else if use_cpu {
+        cpu(config).unwrap()
+    } else {
+        gpu(config).unwrap()
+    }
+}
 
-    gpu(args.try_into().unwrap()).unwrap()
+/// Prints the expected number of addresses per solution and, given a rate or
+/// GPU devices to measure one on, the expected time per solution.
+fn print_difficulty(config: Config, rate: Option<f64>, measure: Duration) {
+    let difficulty = create3crunch::difficulty(&config);
+    println!(
+        "probability per address: {:.3e}, expected addresses per solution: {:.3e}",
+        difficulty.probability,
+        difficulty.expected_attempts()
+    );
+    if config.checksum_regex.is_some() || config.min_uppercase.is_some() {
+        println!("the checksum regex and min uppercase aren't accounted for");
+    }
+    if config.address_range.is_some() {
+        println!("the address range isn't accounted for beyond the thresholds");
+    }
+
+    let rate = match rate {
+        Some(rate) => rate,
+        None if !config.gpu_devices.is_empty() => {
+            let result = create3crunch::benchmark(config, measure).unwrap();
+            result.hashes_per_second() / 1_000_000.0
+        }
+        None => return,
+    };
+    println!(
+        "expected time per solution at {:.2} million addresses per second: {}",
+        rate,
+        difficulty.eta(rate)
+    );
 }
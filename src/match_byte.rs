@@ -0,0 +1,81 @@
+use alloy_primitives::{hex, Address};
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// A single byte of the address fixed to a value, given as `POS=HEX`, e.g.
+/// `7=12` for an address whose eighth byte is `0x12`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct MatchByte {
+    /// The index of the byte in the address, between 0 and 19.
+    pub position: u8,
+    pub value: u8,
+}
+
+impl MatchByte {
+    /// The bytes fixing the first and last `nibbles` hex digits of `address`,
+    /// to search for addresses that look like it. As bytes are matched whole,
+    /// `nibbles` has to be even, between 2 and 20.
+    pub fn mimic(address: &Address, nibbles: u8) -> Result<Vec<Self>, String> {
+        if nibbles % 2 != 0 || !(2..=20).contains(&nibbles) {
+            return Err(format!(
+                "Mimic nibbles must be an even number between 2 and 20, got {nibbles}"
+            ));
+        }
+        let bytes = nibbles / 2;
+        Ok((0..bytes)
+            .chain(20 - bytes..20)
+            .map(|position| MatchByte {
+                position,
+                value: address[position as usize],
+            })
+            .collect())
+    }
+
+    /// Whether `address` has the byte at the position.
+    pub fn matches(&self, address: &Address) -> bool {
+        address[self.position as usize] == self.value
+    }
+}
+
+impl FromStr for MatchByte {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (position, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Match byte `{s}` must be given as POS=HEX"))?;
+        let position: u8 = position
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&position| position < 20)
+            .ok_or_else(|| format!("Match byte position in `{s}` must be between 0 and 19"))?;
+        let value = value.trim();
+        let digits = value.strip_prefix("0x").unwrap_or(value);
+        let value = match hex::decode(digits).as_deref() {
+            Ok(&[value]) => value,
+            _ => {
+                return Err(format!(
+                    "Match byte value in `{s}` must be a single hex byte"
+                ))
+            }
+        };
+        Ok(MatchByte { position, value })
+    }
+}
+
+impl TryFrom<String> for MatchByte {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for MatchByte {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}=0x{:02x}", self.position, self.value)
+    }
+}
@@ -0,0 +1,120 @@
+use serde::Serialize;
+use std::fs;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// The contents of the stats file.
+#[derive(Serialize)]
+struct Stats {
+    hashes: u64,
+    found: u64,
+    /// Addresses checked per second, 0 while warming up.
+    hash_rate: f64,
+    cumulative_nonce: u64,
+    uptime_seconds: f64,
+}
+
+/// Counters of a running search exported in the Prometheus text format,
+/// updated along with the dashboard.
+pub(crate) struct Metrics {
+    start: Instant,
+    hashes: AtomicU64,
+    found: AtomicU64,
+    /// The bits of the current hash rate.
+    rate: AtomicU64,
+}
+
+impl Metrics {
+    pub(crate) fn new() -> Self {
+        Metrics {
+            start: Instant::now(),
+            hashes: AtomicU64::new(0),
+            found: AtomicU64::new(0),
+            rate: AtomicU64::new(0),
+        }
+    }
+
+    /// Records the hashes computed and solutions found this run so far, and
+    /// the current hash rate in hashes per second.
+    pub(crate) fn update(&self, hashes: u64, found: u64, rate: f64) {
+        self.hashes.store(hashes, Ordering::Relaxed);
+        self.found.store(found, Ordering::Relaxed);
+        self.rate.store(rate.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Answers every connection on `listener` with the current metrics until
+    /// `stop` returns `true`, polling for connections so it can stop promptly.
+    pub(crate) fn serve(&self, listener: &TcpListener, stop: impl Fn() -> bool) -> io::Result<()> {
+        listener.set_nonblocking(true)?;
+        while !stop() {
+            match listener.accept() {
+                // a misbehaving scraper shouldn't bring down the search
+                Ok((stream, _)) => drop(self.respond(stream)),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the counters and the devices' summed `cumulative_nonce` to the
+    /// JSON file at `path`. It's written to a temporary file first and renamed
+    /// over the previous one, so readers never see a partial file.
+    pub(crate) fn write_stats(&self, path: &str, cumulative_nonce: u64) -> io::Result<()> {
+        let stats = Stats {
+            hashes: self.hashes.load(Ordering::Relaxed),
+            found: self.found.load(Ordering::Relaxed),
+            hash_rate: f64::from_bits(self.rate.load(Ordering::Relaxed)),
+            cumulative_nonce,
+            uptime_seconds: self.start.elapsed().as_secs_f64(),
+        };
+        let tmp_path = format!("{path}.tmp");
+        fs::write(&tmp_path, serde_json::to_string(&stats)?)?;
+        fs::rename(&tmp_path, path)
+    }
+
+    fn respond(&self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+
+        // every request is answered with the metrics, regardless of its path
+        let mut request = [0; 1024];
+        let _len = stream.read(&mut request)?;
+
+        let body = self.render();
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\n\
+             Content-Type: text/plain; version=0.0.4\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    }
+
+    fn render(&self) -> String {
+        format!(
+            "# HELP create3crunch_hashes_total Addresses checked this run.\n\
+             # TYPE create3crunch_hashes_total counter\n\
+             create3crunch_hashes_total {}\n\
+             # HELP create3crunch_solutions_found_total Solutions found this run.\n\
+             # TYPE create3crunch_solutions_found_total counter\n\
+             create3crunch_solutions_found_total {}\n\
+             # HELP create3crunch_hash_rate Addresses checked per second.\n\
+             # TYPE create3crunch_hash_rate gauge\n\
+             create3crunch_hash_rate {}\n\
+             # HELP create3crunch_uptime_seconds Seconds since the search started.\n\
+             # TYPE create3crunch_uptime_seconds gauge\n\
+             create3crunch_uptime_seconds {}\n",
+            self.hashes.load(Ordering::Relaxed),
+            self.found.load(Ordering::Relaxed),
+            f64::from_bits(self.rate.load(Ordering::Relaxed)),
+            self.start.elapsed().as_secs_f64()
+        )
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{output, Config, Solution};
+use alloy_primitives::hex;
+use console::Term;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// What happens to a notification command while every runner is busy and
+/// the queue in front of them is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyOverflow {
+    /// The command isn't run, the solution is still written to the output
+    /// file.
+    #[default]
+    Drop,
+    /// The search waits until a runner is free.
+    Block,
+}
+
+/// Notifies the user of high-value solutions.
+pub(crate) struct Notifier {
+    threshold: Option<u8>,
+    cmd: Option<String>,
+    queue: Option<SyncSender<Command>>,
+    overflow: NotifyOverflow,
+    /// Where the bell rings, which isn't stdout when solutions are written
+    /// there.
+    term: Term,
+}
+
+impl Notifier {
+    /// Starts `notify_concurrency` threads to run the notification command
+    /// on, if one is configured. They exit once the Notifier is dropped and
+    /// the queued commands ran.
+    pub(crate) fn new(config: &Config) -> Self {
+        let queue = config.notify_cmd.as_ref().map(|_| {
+            let runners = config.notify_concurrency as usize;
+            let (queue, commands) = mpsc::sync_channel::<Command>(runners);
+            let commands = Arc::new(Mutex::new(commands));
+            for _ in 0..runners {
+                let commands = commands.clone();
+                std::thread::spawn(move || loop {
+                    let command = commands.lock().unwrap().recv();
+                    match command {
+                        Ok(mut command) => run(&mut command),
+                        Err(_) => break,
+                    }
+                });
+            }
+            queue
+        });
+
+        Notifier {
+            threshold: config.notify_threshold,
+            cmd: config.notify_cmd.clone(),
+            queue,
+            overflow: config.notify_overflow,
+            term: output::status_term(&config.output_file),
+        }
+    }
+
+    /// Rings the terminal bell and runs the configured notification command if
+    /// `solution` has at least as many zero bytes as the notification threshold.
+    ///
+    /// The command gets the address and reward as arguments, and additionally
+    /// the salt through the environment. It runs on one of a few runner
+    /// threads so a slow command doesn't hold up the search, and a burst of
+    /// solutions can't start an unbounded number of them.
+    pub(crate) fn notify(&self, solution: &Solution) {
+        match self.threshold {
+            Some(threshold) if solution.total_zeros >= threshold as usize => {}
+            _ => return,
+        }
+
+        let _ = self.term.write_str("\x07");
+
+        if let (Some(cmd), Some(queue)) = (&self.cmd, &self.queue) {
+            let mut command = Command::new(cmd);
+            command
+                .arg(solution.address.to_string())
+                .arg(&solution.reward)
+                .env("CREATE3CRUNCH_ADDRESS", solution.address.to_string())
+                .env("CREATE3CRUNCH_REWARD", &solution.reward)
+                .env(
+                    "CREATE3CRUNCH_SALT",
+                    format!("0x{}", hex::encode(solution.create2_salt)),
+                );
+            match self.overflow {
+                NotifyOverflow::Block => {
+                    let _ = queue.send(command);
+                }
+                NotifyOverflow::Drop => {
+                    if let Err(TrySendError::Full(_)) = queue.try_send(command) {
+                        warn!(
+                            address = %solution.address,
+                            "notification queue is full, dropped notification command"
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn run(command: &mut Command) {
+    match command.status() {
+        Ok(status) if !status.success() => {
+            warn!(%status, "notification command failed");
+        }
+        Ok(_) => {}
+        Err(err) => warn!(%err, "couldn't run notification command"),
+    }
+}
@@ -0,0 +1,476 @@
+use crate::{Config, Solution};
+use alloy_primitives::{hex, U160};
+use console::{Style, Term};
+use fs4::FileExt;
+use rustc_hash::FxHashSet;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, prelude::*, ErrorKind, SeekFrom, Stdout};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How solutions are written to the output file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// `0x{salt} ({nonce}) => {address} => {reward} (proxy {proxy_address})`
+    #[default]
+    Text,
+    /// One JSON object per line.
+    Jsonl,
+    /// A Solidity constant per line, to paste into a Foundry script:
+    /// `bytes32 constant SALT_{address} = 0x{salt}; // => {address} (nonce {nonce}) => {reward}`
+    Solidity,
+}
+
+/// A line of text with `{placeholder}`s substituted per solution, replacing
+/// the default line of the `text` output format. The placeholders are
+/// `address` (or `deployed_address`), `address_value` (the address as a
+/// decimal integer), `proxy_address`, `salt`, `nonce`, `total_zeros`,
+/// `leading_zeros`, `leading_zero_nibbles` (or `leading_checksum_zeros`, the
+/// same count), `uppercase`, `reward`, `init_code_hash` (of the deploy proxy
+/// in create3 mode), `matched` (the comma-separated conditions met), `id`
+/// (empty without a tag) and `timestamp`, the default line being
+/// `{salt} ({nonce}) => {address} => {reward} (proxy {proxy_address})`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct OutputTemplate {
+    source: String,
+    parts: Vec<TemplatePart>,
+}
+
+#[derive(Clone, Debug)]
+enum TemplatePart {
+    Literal(String),
+    Address,
+    AddressValue,
+    ProxyAddress,
+    Salt,
+    Nonce,
+    TotalZeros,
+    LeadingZeros,
+    LeadingZeroNibbles,
+    Uppercase,
+    Reward,
+    InitCodeHash,
+    Matched,
+    Id,
+    Timestamp,
+}
+
+impl OutputTemplate {
+    /// The template as it was given.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    fn render(&self, solution: &Solution, id: Option<&str>) -> String {
+        let mut line = String::new();
+        for part in &self.parts {
+            match part {
+                TemplatePart::Literal(text) => line.push_str(text),
+                TemplatePart::Address => line.push_str(&solution.address.to_string()),
+                TemplatePart::AddressValue => line.push_str(&address_value(solution)),
+                TemplatePart::ProxyAddress => line.push_str(&solution.proxy_address.to_string()),
+                TemplatePart::Salt => {
+                    line.push_str(&format!("0x{}", hex::encode(solution.create2_salt)))
+                }
+                TemplatePart::Nonce => line.push_str(&solution.nonce.to_string()),
+                TemplatePart::TotalZeros => line.push_str(&solution.total_zeros.to_string()),
+                TemplatePart::LeadingZeros => line.push_str(&solution.leading_zeros.to_string()),
+                TemplatePart::LeadingZeroNibbles => {
+                    line.push_str(&solution.leading_zero_nibbles.to_string())
+                }
+                TemplatePart::Uppercase => line.push_str(&solution.uppercase_letters.to_string()),
+                TemplatePart::Reward => line.push_str(&solution.reward),
+                TemplatePart::InitCodeHash => line.push_str(&solution.init_code_hash.to_string()),
+                TemplatePart::Matched => line.push_str(&solution.matched.join(",")),
+                TemplatePart::Id => line.push_str(id.unwrap_or_default()),
+                TemplatePart::Timestamp => line.push_str(&unix_timestamp().to_string()),
+            }
+        }
+        line
+    }
+}
+
+impl FromStr for OutputTemplate {
+    type Err = String;
+
+    /// Fails on unknown placeholders and unclosed braces, rather than writing
+    /// them out literally.
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let mut parts = vec![];
+        let mut rest = source;
+        while let Some(open) = rest.find('{') {
+            if open > 0 {
+                parts.push(TemplatePart::Literal(rest[..open].to_string()));
+            }
+            let close = rest[open..]
+                .find('}')
+                .ok_or_else(|| format!("Unclosed `{{` in output template `{source}`"))?;
+            let part = match &rest[open + 1..open + close] {
+                "address" | "deployed_address" => TemplatePart::Address,
+                "address_value" => TemplatePart::AddressValue,
+                "proxy_address" => TemplatePart::ProxyAddress,
+                "salt" => TemplatePart::Salt,
+                "nonce" => TemplatePart::Nonce,
+                "total_zeros" => TemplatePart::TotalZeros,
+                "leading_zeros" => TemplatePart::LeadingZeros,
+                "leading_zero_nibbles" | "leading_checksum_zeros" => {
+                    TemplatePart::LeadingZeroNibbles
+                }
+                "uppercase" => TemplatePart::Uppercase,
+                "reward" => TemplatePart::Reward,
+                "init_code_hash" => TemplatePart::InitCodeHash,
+                "matched" => TemplatePart::Matched,
+                "id" => TemplatePart::Id,
+                "timestamp" => TemplatePart::Timestamp,
+                name => {
+                    return Err(format!(
+                        "Unknown placeholder `{{{name}}}` in output template, expected one of \
+                         address, deployed_address, address_value, proxy_address, salt, nonce, \
+                         total_zeros, leading_zeros, leading_zero_nibbles, uppercase, reward, \
+                         init_code_hash, matched, id or timestamp"
+                    ))
+                }
+            };
+            parts.push(part);
+            rest = &rest[open + close + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Literal(rest.to_string()));
+        }
+
+        Ok(OutputTemplate {
+            source: source.to_string(),
+            parts,
+        })
+    }
+}
+
+impl TryFrom<String> for OutputTemplate {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        source.parse()
+    }
+}
+
+/// The address of `solution` as a decimal integer, for comparing it against
+/// an address range.
+fn address_value(solution: &Solution) -> String {
+    U160::from_be_bytes(solution.address.0 .0).to_string()
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// The line of the `solidity` output format, named after the address so the
+/// constants of an output file don't clash.
+fn solidity_constant(solution: &Solution) -> String {
+    format!(
+        "bytes32 constant SALT_{} = 0x{}; // => {} (nonce {}) => {}",
+        hex::encode(solution.address),
+        hex::encode(solution.create2_salt),
+        solution.address,
+        solution.nonce,
+        solution.reward
+    )
+}
+
+/// The line the `jsonl` output format writes for `solution`, without an id.
+pub(crate) fn solution_json(solution: &Solution) -> String {
+    serde_json::to_string(&JsonSolution::from(solution)).expect("Couldn't serialize solution.")
+}
+
+/// A solution as written in the `jsonl` output format.
+#[derive(Serialize)]
+struct JsonSolution {
+    salt: String,
+    nonce: u64,
+    address: String,
+    deployed_address: String,
+    address_value: String,
+    proxy_address: String,
+    total_zeros: usize,
+    leading_zeros: usize,
+    leading_zero_nibbles: usize,
+    uppercase_letters: usize,
+    reward: String,
+    reward_score: f64,
+    init_code_hash: String,
+    matched: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    timestamp: u64,
+}
+
+impl From<&Solution> for JsonSolution {
+    fn from(solution: &Solution) -> Self {
+        JsonSolution {
+            salt: format!("0x{}", hex::encode(solution.create2_salt)),
+            nonce: solution.nonce,
+            address: solution.address.to_string(),
+            deployed_address: solution.address.to_string(),
+            address_value: address_value(solution),
+            proxy_address: solution.proxy_address.to_string(),
+            total_zeros: solution.total_zeros,
+            leading_zeros: solution.leading_zeros,
+            leading_zero_nibbles: solution.leading_zero_nibbles,
+            uppercase_letters: solution.uppercase_letters,
+            reward: solution.reward.clone(),
+            reward_score: solution.reward_score,
+            init_code_hash: solution.init_code_hash.to_string(),
+            matched: solution.matched.clone(),
+            id: None,
+            timestamp: unix_timestamp(),
+        }
+    }
+}
+
+/// Whether `path` names stdout rather than a regular file, so solutions can be
+/// piped into another process.
+pub(crate) fn is_stdout(path: &str) -> bool {
+    path == "-" || path == "/dev/stdout"
+}
+
+/// The terminal status messages are written to, which is stderr when solutions
+/// are written to stdout so they don't end up in the stream.
+pub(crate) fn status_term(path: &str) -> Term {
+    if is_stdout(path) {
+        Term::stderr()
+    } else {
+        Term::stdout()
+    }
+}
+
+/// Whether solutions are colored on `term`, which needs a terminal supporting
+/// colors and `NO_COLOR` to be unset.
+pub(crate) fn colors_enabled(term: &Term) -> bool {
+    term.features().colors_supported() && std::env::var_os("NO_COLOR").is_none()
+}
+
+/// The style of a found solution on the dashboard by its rarity, see
+/// [`Reward::rarity`](crate::Reward::rarity): dim for common solutions,
+/// yellow for rare and bright green for very rare ones.
+pub(crate) fn rarity_style(rarity: f64, colors: bool) -> Style {
+    let style = Style::new().force_styling(colors);
+    if rarity >= 1e12 {
+        style.green().bright().bold()
+    } else if rarity >= 1e6 {
+        style.yellow()
+    } else {
+        style.dim()
+    }
+}
+
+/// Reads back the salts of the solutions already written to the output file at
+/// `path`, in any format, so they aren't written again. Text and Solidity
+/// lines are searched for the first 32-byte hex word so lines of any output
+/// template are found, lines without one are skipped.
+pub(crate) fn read_salts(path: &str) -> io::Result<FxHashSet<[u8; 32]>> {
+    if is_stdout(path) {
+        return Ok(FxHashSet::default());
+    }
+
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(FxHashSet::default()),
+        Err(err) => return Err(err),
+    };
+
+    let salts = contents
+        .lines()
+        .filter_map(|line| {
+            let salt = if line.starts_with('{') {
+                let value: serde_json::Value = serde_json::from_str(line).ok()?;
+                value.get("salt")?.as_str()?.to_string()
+            } else {
+                line.split(|c: char| !c.is_ascii_alphanumeric())
+                    .find(|word| word.len() == 66 && word.starts_with("0x"))?
+                    .to_string()
+            };
+            hex::decode(salt).ok()?.try_into().ok()
+        })
+        .collect();
+
+    Ok(salts)
+}
+
+/// Reads back up to the last `count` lines of the output file at `path`, so
+/// the dashboard can show what previous runs found. Only the end of the file
+/// is read, however large it grew.
+pub(crate) fn tail_lines(path: &str, count: usize) -> io::Result<Vec<String>> {
+    /// How many bytes from the end of the file are searched for lines.
+    const TAIL_BYTES: u64 = 64 * 1024;
+
+    if is_stdout(path) {
+        return Ok(vec![]);
+    }
+
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err),
+    };
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(TAIL_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+    let mut tail = vec![];
+    file.read_to_end(&mut tail)?;
+    let tail = String::from_utf8_lossy(&tail);
+
+    // the first line is cut off unless the whole file was read
+    let mut lines: Vec<&str> = tail.lines().skip((start > 0) as usize).collect();
+    lines.retain(|line| !line.trim().is_empty());
+    let skip = lines.len().saturating_sub(count);
+    Ok(lines[skip..].iter().map(|line| line.to_string()).collect())
+}
+
+/// Where found solutions are written to.
+enum Target {
+    File(File),
+    Stdout(Stdout),
+}
+
+/// Numbers the solutions of a run for the Config's `tag`.
+struct RunIds {
+    /// The tag and the random id of the run, `TAG:RUN`.
+    run: String,
+    written: AtomicU64,
+}
+
+impl RunIds {
+    fn new(tag: &str) -> Self {
+        RunIds {
+            run: format!("{tag}:{:08x}", rand::random::<u32>()),
+            written: AtomicU64::new(0),
+        }
+    }
+
+    /// The id of the next solution, counting from 1.
+    fn next(&self) -> String {
+        let seq = self.written.fetch_add(1, Ordering::Relaxed) + 1;
+        format!("{}:{seq}", self.run)
+    }
+}
+
+/// The file found solutions are appended to.
+pub(crate) struct OutputFile {
+    target: Target,
+    path: String,
+    format: OutputFormat,
+    template: Option<OutputTemplate>,
+    durable: bool,
+    ids: Option<RunIds>,
+}
+
+impl OutputFile {
+    /// Opens the Config's output file, creating it and any missing parent
+    /// directories if necessary. `-` and `/dev/stdout` write to stdout
+    /// instead. A durable file is synced to disk after every write.
+    pub(crate) fn open(config: &Config) -> io::Result<Self> {
+        let path = &config.output_file;
+        let target = if is_stdout(path) {
+            Target::Stdout(io::stdout())
+        } else {
+            let describe = |err: io::Error| {
+                io::Error::new(
+                    err.kind(),
+                    format!("Couldn't create or open output file `{path}`: {err}"),
+                )
+            };
+            if let Some(parent) = Path::new(path).parent() {
+                fs::create_dir_all(parent).map_err(describe)?;
+            }
+            let file = OpenOptions::new()
+                .append(true)
+                .create(true)
+                .read(true)
+                .open(path)
+                .map_err(describe)?;
+            Target::File(file)
+        };
+        Ok(OutputFile {
+            target,
+            path: path.to_string(),
+            format: config.output_format,
+            template: config.output_template.clone(),
+            durable: config.durable,
+            ids: config.tag.as_deref().map(RunIds::new),
+        })
+    }
+
+    /// Whether solutions are written to stdout rather than to a file.
+    pub(crate) fn is_stdout(&self) -> bool {
+        matches!(self.target, Target::Stdout(_))
+    }
+
+    /// Appends `solution` to the file while holding an exclusive lock on it,
+    /// or writes it to stdout and flushes it right away. A durable file is
+    /// synced before the lock is released, so the next writer only gets the
+    /// lock once the solution reached the disk.
+    pub(crate) fn write(&self, solution: &Solution) {
+        let id = self.ids.as_ref().map(RunIds::next);
+        let line = match (self.format, &self.template) {
+            (OutputFormat::Text, Some(template)) => template.render(solution, id.as_deref()),
+            (OutputFormat::Text, None) => match &id {
+                Some(id) => format!("{solution} [{id}]"),
+                None => solution.to_string(),
+            },
+            (OutputFormat::Solidity, _) => match &id {
+                Some(id) => format!("{} [{id}]", solidity_constant(solution)),
+                None => solidity_constant(solution),
+            },
+            (OutputFormat::Jsonl, _) => {
+                let json = JsonSolution {
+                    id,
+                    ..JsonSolution::from(solution)
+                };
+                serde_json::to_string(&json).expect("Couldn't serialize solution.")
+            }
+        };
+
+        match &self.target {
+            Target::File(file) => {
+                file.lock_exclusive().expect("Couldn't lock file.");
+
+                writeln!(&*file, "{line}")
+                    .unwrap_or_else(|_| panic!("Couldn't write to `{}` file.", self.path));
+                if self.durable {
+                    (&*file)
+                        .flush()
+                        .and_then(|()| file.sync_data())
+                        .unwrap_or_else(|_| panic!("Couldn't sync `{}` file.", self.path));
+                }
+
+                FileExt::unlock(file).expect("Couldn't unlock file.");
+            }
+            Target::Stdout(stdout) => {
+                let mut stdout = stdout.lock();
+                writeln!(stdout, "{line}")
+                    .and_then(|()| stdout.flush())
+                    .expect("Couldn't write to stdout.");
+            }
+        }
+    }
+
+    /// Makes sure nothing is left buffered or locked once the search is over.
+    pub(crate) fn close(&self) {
+        match &self.target {
+            Target::File(file) => {
+                (&*file)
+                    .flush()
+                    .unwrap_or_else(|_| panic!("Couldn't flush `{}` file.", self.path));
+                FileExt::unlock(file).expect("Couldn't unlock file.");
+            }
+            Target::Stdout(stdout) => stdout.lock().flush().expect("Couldn't flush stdout."),
+        }
+    }
+}
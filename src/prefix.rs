@@ -0,0 +1,241 @@
+use alloy_primitives::{hex, Address};
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+
+/// How many bytes of constant memory the kernel's prefix tables may take.
+/// OpenCL only guarantees 64 KiB of it, which the initcode hashes share, and
+/// every candidate is compared against every prefix. Larger lists are checked
+/// against a Bloom filter instead.
+const MAX_TABLE_BYTES: usize = 32 * 1024;
+
+/// How many bits the Bloom filter has per prefix. Along with `BLOOM_HASHES`
+/// this lets about one in 10^7 addresses through per prefix length that match
+/// none of the prefixes, few enough that they don't crowd out the solutions of
+/// a batch.
+const BLOOM_BITS_PER_PREFIX: usize = 256;
+
+/// How many bits of the Bloom filter each prefix sets.
+pub(crate) const BLOOM_HASHES: u32 = 4;
+
+/// The most nibbles of the address the Bloom filter is keyed by.
+const MAX_BLOOM_KEY_NIBBLES: usize = 8;
+
+/// A Bloom filter of a list of prefixes, which the kernel checks addresses
+/// against in place of the prefix tables. Every prefix is keyed by its own
+/// length, so a few short prefixes don't cut the long ones down to a key
+/// most addresses share.
+pub(crate) struct PrefixBloom {
+    /// The distinct lengths of the prefixes in ascending order, each at most
+    /// `MAX_BLOOM_KEY_NIBBLES`. An address is looked up once per length.
+    pub(crate) key_nibbles: Vec<usize>,
+    /// The filter's bits, a power of two of them.
+    pub(crate) words: Vec<u32>,
+}
+
+impl PrefixBloom {
+    /// Whether `prefixes` are too many for the kernel's prefix tables.
+    pub(crate) fn needed(prefixes: &[AddressPrefix]) -> bool {
+        let byte_len = prefixes.iter().map(AddressPrefix::byte_len).max();
+        byte_len.map_or(false, |byte_len| {
+            2 * prefixes.len() * byte_len > MAX_TABLE_BYTES
+        })
+    }
+
+    /// Builds the filter of `prefixes` if they're too many for the kernel's
+    /// prefix tables.
+    pub(crate) fn new(prefixes: &[AddressPrefix]) -> Option<Self> {
+        if !Self::needed(prefixes) {
+            return None;
+        }
+
+        let key_len = |prefix: &AddressPrefix| prefix.nibbles.min(MAX_BLOOM_KEY_NIBBLES);
+        let mut key_nibbles: Vec<usize> = prefixes.iter().map(key_len).collect();
+        key_nibbles.sort_unstable();
+        key_nibbles.dedup();
+        let bits = (prefixes.len() * BLOOM_BITS_PER_PREFIX).next_power_of_two();
+        let mut bloom = PrefixBloom {
+            key_nibbles,
+            words: vec![0; bits / 32],
+        };
+        for prefix in prefixes {
+            let nibbles = key_len(prefix);
+            let key = bloom_key(&prefix.bytes, nibbles);
+            for i in 0..BLOOM_HASHES {
+                let bit = bloom.bit(key, nibbles, i);
+                bloom.words[bit as usize / 32] |= 1 << (bit % 32);
+            }
+        }
+        Some(bloom)
+    }
+
+    /// The mask reducing a hash to a bit of the filter.
+    pub(crate) fn mask(&self) -> u32 {
+        (self.words.len() * 32 - 1) as u32
+    }
+
+    /// The `i`th bit the `nibbles` long `key` sets, computed the same way as
+    /// by the kernel's `hasPrefixBloom`. The length is mixed in so that keys
+    /// of different lengths with the same value set different bits.
+    fn bit(&self, key: u32, nibbles: usize, i: u32) -> u32 {
+        let key = key ^ (nibbles as u32).wrapping_mul(0xc2b2ae35);
+        let h1 = key.wrapping_mul(0x9e3779b1);
+        let h2 = (key ^ (key >> 15)).wrapping_mul(0x85ebca6b) | 1;
+        let mut x = h1.wrapping_add(i.wrapping_mul(h2));
+        x ^= x >> 16;
+        x = x.wrapping_mul(0x7feb352d);
+        x ^= x >> 15;
+        x & self.mask()
+    }
+}
+
+/// The first `nibbles` nibbles of `bytes` as a number.
+fn bloom_key(bytes: &[u8], nibbles: usize) -> u32 {
+    (0..nibbles).fold(0, |key, i| {
+        let nibble = if i % 2 == 1 {
+            bytes[i / 2] & 0x0f
+        } else {
+            bytes[i / 2] >> 4
+        };
+        key << 4 | nibble as u32
+    })
+}
+
+/// The first up to 40 hex digits of an address, e.g. `0xc0ffee`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AddressPrefix {
+    /// The digits packed into bytes, an odd last digit in the high nibble.
+    bytes: Vec<u8>,
+    nibbles: usize,
+}
+
+impl AddressPrefix {
+    /// Loads a list of prefixes from a file with one prefix per line. Blank
+    /// lines and lines starting with `#` are skipped, and parse errors name
+    /// the line.
+    pub fn load(path: &str) -> Result<Vec<Self>, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Couldn't read prefix list `{path}`: {err}"))?;
+        let prefixes: Vec<Self> = contents
+            .lines()
+            .map(str::trim)
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(i, line)| {
+                line.parse()
+                    .map_err(|err| format!("Invalid prefix on line {} of `{path}`: {err}", i + 1))
+            })
+            .collect::<Result<_, _>>()?;
+        if prefixes.is_empty() {
+            return Err(format!("Prefix list `{path}` doesn't contain any prefix"));
+        }
+        Ok(prefixes)
+    }
+
+    /// Whether `address` starts with the prefix.
+    pub fn matches(&self, address: &Address) -> bool {
+        let (mask, target) = self.mask_and_target(self.bytes.len());
+        address
+            .iter()
+            .zip(mask.iter().zip(&target))
+            .all(|(byte, (mask, target))| byte & mask == *target)
+    }
+
+    /// The number of hex digits the prefix fixes.
+    pub fn nibbles(&self) -> usize {
+        self.nibbles
+    }
+
+    /// The number of address bytes the prefix touches.
+    pub(crate) fn byte_len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// The masks and values the first `len` bytes of a matching address
+    /// have, where `len` is at least [`Self::byte_len`]. Bytes past the
+    /// prefix have an empty mask so they always match.
+    pub(crate) fn mask_and_target(&self, len: usize) -> (Vec<u8>, Vec<u8>) {
+        let mut mask = vec![0u8; len];
+        let mut target = vec![0u8; len];
+        for (i, &byte) in self.bytes.iter().enumerate() {
+            mask[i] = if self.nibbles == 2 * i + 1 {
+                0xf0
+            } else {
+                0xff
+            };
+            target[i] = byte & mask[i];
+        }
+        (mask, target)
+    }
+}
+
+impl FromStr for AddressPrefix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix("0x").unwrap_or(s);
+        if digits.is_empty() || digits.len() > 40 {
+            return Err(format!(
+                "Prefix `{s}` must have between 1 and 40 hex digits"
+            ));
+        }
+        let nibbles = digits.len();
+        // pad an odd digit out to a full byte, the mask ignores the padding
+        let padded = if nibbles % 2 == 1 {
+            format!("{digits}0")
+        } else {
+            digits.to_string()
+        };
+        let bytes = hex::decode(padded).map_err(|_| format!("Prefix `{s}` isn't hex"))?;
+        Ok(AddressPrefix { bytes, nibbles })
+    }
+}
+
+impl fmt::Display for AddressPrefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", &hex::encode(&self.bytes)[..self.nibbles])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::keccak256;
+
+    /// Whether the kernel's `hasPrefixBloom` lets `address` through.
+    fn passes(bloom: &PrefixBloom, address: &Address) -> bool {
+        bloom.key_nibbles.iter().any(|&nibbles| {
+            let key = bloom_key(address.as_slice(), nibbles);
+            (0..BLOOM_HASHES).all(|i| {
+                let bit = bloom.bit(key, nibbles, i);
+                bloom.words[bit as usize / 32] & (1 << (bit % 32)) != 0
+            })
+        })
+    }
+
+    #[test]
+    fn bloom_keys_prefixes_by_their_own_length() {
+        let mut prefixes: Vec<AddressPrefix> = (0..5000u32)
+            .map(|i| {
+                format!("{:08x}", i.wrapping_mul(0x9e3779b1))
+                    .parse()
+                    .unwrap()
+            })
+            .collect();
+        // keyed by the shortest prefix, this would let every address through
+        prefixes.push("0x00".parse().unwrap());
+        let bloom = PrefixBloom::new(&prefixes).unwrap();
+        assert_eq!(bloom.key_nibbles, [2, 8]);
+
+        for prefix in &prefixes {
+            let mut address = Address::ZERO;
+            address[..prefix.byte_len()].copy_from_slice(&prefix.bytes);
+            assert!(passes(&bloom, &address), "{prefix} doesn't pass");
+        }
+        let passed = (0..20_000u32)
+            .map(|i| Address::from_word(keccak256(i.to_be_bytes())))
+            .filter(|address| address[0] != 0 && passes(&bloom, address))
+            .count();
+        assert!(passed < 5, "{passed} addresses passed");
+    }
+}
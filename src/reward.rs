@@ -1,7 +1,30 @@
 use rustc_hash::FxHashMap;
+use std::borrow::Cow;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+
+/// An entry of the reward table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tier {
+    /// The zero counts of the addresses the tier is for, `leading_zeros * 20
+    /// + total_zeros`.
+    pub key: usize,
+    /// The reward of the addresses, the built-in number or whatever the
+    /// reward file gives them.
+    pub label: Cow<'static, str>,
+    /// The built-in reward as a number, 0 for keys only the reward file has.
+    /// Rarer addresses always have a higher rarity, whatever their label.
+    pub rarity: f64,
+}
 
 pub struct Reward {
-    reward: FxHashMap<usize, &'static str>,
+    tiers: FxHashMap<usize, Tier>,
+}
+
+impl Default for Reward {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Reward {
@@ -208,11 +231,119 @@ impl Reward {
             (399, "340282366920938463463374607431768211456"),
             (420, "87112285931760246646623899502532662132736"),
         ]);
-        Reward { reward }
+        let tiers = reward
+            .into_iter()
+            .map(|(key, value)| {
+                let tier = Tier {
+                    key,
+                    label: Cow::Borrowed(value),
+                    rarity: value.parse().unwrap(),
+                };
+                (key, tier)
+            })
+            .collect();
+        Reward { tiers }
+    }
+
+    /// Loads a reward table from a TOML file mapping keys to reward strings,
+    /// e.g. `45 = "84"`. Keys missing from the file keep their built-in value.
+    pub fn from_file(path: &str) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let table: FxHashMap<String, String> = toml::from_str(&contents).map_err(|err| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Invalid reward file `{path}`: {err}"),
+            )
+        })?;
+
+        let mut rewards = Reward::new();
+        for (key, value) in table {
+            let key = key.parse().map_err(|_| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Invalid key `{key}` in reward file `{path}`."),
+                )
+            })?;
+            let tier = rewards.tiers.entry(key).or_insert_with(|| Tier {
+                key,
+                label: Cow::Borrowed(""),
+                rarity: 0.0,
+            });
+            tier.label = Cow::Owned(value);
+        }
+        Ok(rewards)
     }
 
     #[inline]
-    pub fn get(&self, value: &usize) -> Option<&'static str> {
-        self.reward.get(value).copied()
+    pub fn get(&self, value: &usize) -> Option<&str> {
+        self.tiers.get(value).map(|tier| tier.label.as_ref())
+    }
+
+    /// Every entry of the table from the least to the most rare, entries of
+    /// the same rarity ordered by key.
+    pub fn tiers(&self) -> Vec<&Tier> {
+        let mut tiers: Vec<&Tier> = self.tiers.values().collect();
+        tiers.sort_by(|a, b| a.rarity.total_cmp(&b.rarity).then(a.key.cmp(&b.key)));
+        tiers
+    }
+
+    /// The reward as a number for sorting and thresholding, rarer addresses
+    /// score higher. Keys without a numeric reward score 0.
+    pub fn score(&self, value: &usize) -> f64 {
+        self.get(value)
+            .and_then(|reward| reward.parse().ok())
+            .unwrap_or(0.0)
+    }
+
+    /// The built-in reward of a key as a number, which orders keys even when
+    /// the reward file gives them names rather than numbers.
+    pub fn rarity(&self, value: &usize) -> f64 {
+        self.tiers.get(value).map_or(0.0, |tier| tier.rarity)
+    }
+
+    /// The rarity a solution needs to be worth at least `reward`, either a
+    /// number or a reward of the table, e.g. a name from the reward file. A
+    /// name given to several keys stands for the least rare of them.
+    pub fn tier(&self, reward: &str) -> Option<f64> {
+        if let Ok(number) = reward.parse() {
+            return Some(number);
+        }
+        self.tiers
+            .values()
+            .filter(|tier| tier.label == reward)
+            .map(|tier| tier.rarity)
+            .reduce(f64::min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_rarity_is_strictly_monotonic() {
+        let rewards = Reward::new();
+        let tiers = rewards.tiers();
+        for pair in tiers.windows(2) {
+            assert!(
+                pair[0].rarity < pair[1].rarity,
+                "keys {} and {} tie",
+                pair[0].key,
+                pair[1].key
+            );
+        }
+        // another zero byte, or another leading one, is always rarer
+        for tier in &tiers {
+            for rarer in [tier.key + 1, tier.key + 20] {
+                if let Some(rarer) = rewards.tiers.get(&rarer) {
+                    assert!(
+                        tier.rarity < rarer.rarity,
+                        "key {} isn't rarer than key {}",
+                        rarer.key,
+                        tier.key
+                    );
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,154 @@
+use alloy_primitives::{Bytes, FixedBytes};
+use rand::Rng;
+use rustc_hash::FxHashMap;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+
+/// The position of a running search on a single device, periodically
+/// persisted so that a restarted miner can pick up where it left off instead
+/// of starting over from a fresh random salt.
+#[derive(Clone, Copy, Debug)]
+pub struct SearchState {
+    /// The random segment of the salt currently being searched, only its first
+    /// `salt_bytes` bytes are used.
+    pub salt: FixedBytes<8>,
+    /// The nonce segment of the next work batch.
+    pub nonce: u32,
+    /// The total number of work batches enqueued so far.
+    pub cumulative_nonce: u64,
+}
+
+impl SearchState {
+    /// A fresh state starting at a random salt of `salt_bytes` bytes drawn
+    /// from `rng`. For more uniformly distributed nonces, the nonce is
+    /// initialized to a random value too.
+    pub fn random<R: Rng + ?Sized>(rng: &mut R, salt_bytes: u8) -> Self {
+        let mut salt = FixedBytes::ZERO;
+        rng.fill(&mut salt[..salt_bytes as usize]);
+        SearchState {
+            salt,
+            nonce: rng.gen_range(0..crate::nonce_limit(salt_bytes)) as u32,
+            cumulative_nonce: 0,
+        }
+    }
+
+    /// A fresh state at the first nonce of the salt whose `salt_bytes` bytes
+    /// spell out `counter` in big-endian, for searching salts in order.
+    pub fn sequential(counter: u64, salt_bytes: u8) -> Self {
+        let salt_bytes = salt_bytes as usize;
+        let mut salt = FixedBytes::ZERO;
+        salt[..salt_bytes].copy_from_slice(&counter.to_be_bytes()[8 - salt_bytes..]);
+        SearchState {
+            salt,
+            nonce: 0,
+            cumulative_nonce: 0,
+        }
+    }
+
+    /// A fresh state at `nonce` of `salt`, which has to have exactly
+    /// `salt_bytes` bytes, for starting a search at a given point.
+    pub fn at(salt: &[u8], nonce: u32, salt_bytes: u8) -> std::result::Result<Self, String> {
+        if salt.len() != salt_bytes as usize {
+            return Err(format!(
+                "The start salt must have exactly {salt_bytes} bytes, got {}",
+                salt.len()
+            ));
+        }
+        if nonce as u64 >= crate::nonce_limit(salt_bytes) {
+            return Err(format!(
+                "Start nonce {nonce} doesn't fit in the {} nonce bytes left by {salt_bytes} salt bytes",
+                8 - salt_bytes
+            ));
+        }
+        let mut padded = FixedBytes::ZERO;
+        padded[..salt.len()].copy_from_slice(salt);
+        Ok(SearchState {
+            salt: padded,
+            nonce,
+            cumulative_nonce: 0,
+        })
+    }
+
+    /// The counter the salt spells out, see [`SearchState::sequential`].
+    pub fn counter(&self, salt_bytes: u8) -> u64 {
+        let salt_bytes = salt_bytes as usize;
+        let mut counter = [0u8; 8];
+        counter[8 - salt_bytes..].copy_from_slice(&self.salt[..salt_bytes]);
+        u64::from_be_bytes(counter)
+    }
+
+    /// Loads the state of every device from `path`, keyed by device index. An
+    /// empty map is returned if no state was saved yet.
+    pub fn load(path: &str) -> Result<FxHashMap<u8, Self>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(FxHashMap::default()),
+            Err(err) => return Err(err),
+        };
+
+        let mut states = FxHashMap::default();
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let mut device = None;
+            let mut salt = None;
+            let mut nonce = None;
+            let mut cumulative_nonce = None;
+            for field in line.split_whitespace() {
+                let (key, value) = field.split_once('=').ok_or_else(|| invalid(path, line))?;
+                match key {
+                    "device" => device = value.parse().ok(),
+                    "salt" => salt = value.parse().ok().and_then(pad_salt),
+                    "nonce" => nonce = value.parse().ok(),
+                    "cumulative_nonce" => cumulative_nonce = value.parse().ok(),
+                    _ => return Err(invalid(path, line)),
+                }
+            }
+
+            match (device, salt, nonce, cumulative_nonce) {
+                (Some(device), Some(salt), Some(nonce), Some(cumulative_nonce)) => {
+                    states.insert(
+                        device,
+                        SearchState {
+                            salt,
+                            nonce,
+                            cumulative_nonce,
+                        },
+                    );
+                }
+                _ => return Err(invalid(path, line)),
+            }
+        }
+
+        Ok(states)
+    }
+
+    /// Atomically writes the state of every device to `path` by writing to a
+    /// temporary file first and renaming it over the previous state.
+    pub fn save(path: &str, states: &[(u8, Self)]) -> Result<()> {
+        let mut contents = String::new();
+        for (device, state) in states {
+            contents.push_str(&format!(
+                "device={} salt={} nonce={} cumulative_nonce={}\n",
+                device, state.salt, state.nonce, state.cumulative_nonce
+            ));
+        }
+
+        let tmp_path = format!("{path}.tmp");
+        fs::write(&tmp_path, contents)?;
+        fs::rename(&tmp_path, path)
+    }
+}
+
+/// Pads a saved salt to the full segment, states saved before the salt length
+/// was configurable only have 4 bytes.
+fn pad_salt(salt: Bytes) -> Option<FixedBytes<8>> {
+    let mut padded = FixedBytes::ZERO;
+    padded.get_mut(..salt.len())?.copy_from_slice(&salt);
+    Some(padded)
+}
+
+fn invalid(path: &str, line: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("Invalid line `{line}` in search state `{path}`."),
+    )
+}
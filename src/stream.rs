@@ -0,0 +1,113 @@
+use crate::{output, Config, Solution};
+use std::io::{self, ErrorKind};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::time::Duration;
+use tracing::warn;
+use tungstenite::{Error, Message, WebSocket};
+
+/// How many solutions can wait to be sent before further ones are dropped.
+const STREAM_QUEUE: usize = 64;
+
+/// How long a client may take to complete the WebSocket handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How often new clients are accepted and connected ones are read from while
+/// no solutions come in.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Streams solutions as JSON to the WebSocket clients connected to
+/// `ws_addr`, if it's set.
+pub(crate) struct SolutionStream {
+    queue: Option<SyncSender<String>>,
+}
+
+impl SolutionStream {
+    /// Binds `ws_addr` and starts a thread accepting clients and sending them
+    /// solutions. It exits once the SolutionStream is dropped.
+    pub(crate) fn new(config: &Config) -> io::Result<Self> {
+        let Some(addr) = config.ws_addr else {
+            return Ok(SolutionStream { queue: None });
+        };
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+
+        let (queue, solutions) = mpsc::sync_channel(STREAM_QUEUE);
+        std::thread::spawn(move || {
+            if let Err(err) = serve(&listener, &solutions) {
+                warn!(%err, "solution stream stopped");
+            }
+        });
+        Ok(SolutionStream { queue: Some(queue) })
+    }
+
+    /// Queues `solution` for every connected client, dropping it rather than
+    /// holding up the search if the queue is full.
+    pub(crate) fn send(&self, solution: &Solution) {
+        let Some(queue) = &self.queue else {
+            return;
+        };
+        if let Err(TrySendError::Full(_)) = queue.try_send(output::solution_json(solution)) {
+            warn!(
+                address = %solution.address,
+                "solution stream queue is full, dropped solution"
+            );
+        }
+    }
+}
+
+/// Accepts clients on `listener` and sends every solution of `solutions` to
+/// all of them, until the sending side is dropped. A client whose socket
+/// can't take a solution right away is disconnected, so a slow client can't
+/// hold up the others.
+fn serve(listener: &TcpListener, solutions: &Receiver<String>) -> io::Result<()> {
+    let mut clients: Vec<WebSocket<TcpStream>> = vec![];
+    loop {
+        loop {
+            match listener.accept() {
+                // a misbehaving client shouldn't bring down the stream
+                Ok((stream, _)) => clients.extend(handshake(stream)),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+        clients.retain_mut(keep_alive);
+
+        match solutions.recv_timeout(POLL_INTERVAL) {
+            Ok(json) => {
+                clients.retain_mut(|client| client.send(Message::Text(json.clone())).is_ok())
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                for client in &mut clients {
+                    let _ = client.close(None);
+                }
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Completes the client's WebSocket handshake, `None` if it isn't one or
+/// doesn't complete within `HANDSHAKE_TIMEOUT`.
+fn handshake(stream: TcpStream) -> Option<WebSocket<TcpStream>> {
+    stream.set_nonblocking(false).ok()?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(HANDSHAKE_TIMEOUT)).ok()?;
+    let client = tungstenite::accept(stream).ok()?;
+    client.get_ref().set_nonblocking(true).ok()?;
+    Some(client)
+}
+
+/// Reads everything the client sent, which answers its pings and close
+/// frames. Returns whether it's still connected. Its messages are ignored,
+/// the stream only goes one way.
+fn keep_alive(client: &mut WebSocket<TcpStream>) -> bool {
+    loop {
+        match client.read() {
+            Ok(_) => {}
+            Err(Error::Io(err)) if err.kind() == ErrorKind::WouldBlock => return true,
+            Err(_) => return false,
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{output, Config, Create3Variant, Mode, OutputFormat, SaltMode};
+use alloy_primitives::{Address, Bytes, FixedBytes};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The bookkeeping of a finished run, appended to the summary file as a line
+/// of JSON.
+#[derive(Serialize)]
+pub(crate) struct RunSummary<'a> {
+    /// Unix timestamp of when the search started.
+    started_at: u64,
+    /// Unix timestamp of when the search stopped.
+    ended_at: u64,
+    /// The total number of work batches enqueued, including resumed ones.
+    cumulative_nonce: u64,
+    /// Addresses checked this run.
+    hashes: u64,
+    /// Solutions found this run.
+    solutions_found: u64,
+    /// Addresses checked per second, on average.
+    hashes_per_second: f64,
+    /// Solutions found this run per number of zero bytes in their address.
+    zero_byte_histogram: &'a ZeroHistogram,
+    config: ConfigSummary<'a>,
+}
+
+impl<'a> RunSummary<'a> {
+    /// Summarizes a run that started at `started_at` seconds since the epoch
+    /// and ended just now.
+    pub(crate) fn new(
+        config: &'a Config,
+        started_at: f64,
+        cumulative_nonce: u64,
+        hashes: u64,
+        solutions_found: u64,
+        zero_byte_histogram: &'a ZeroHistogram,
+    ) -> Self {
+        let ended_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs_f64();
+        let runtime = ended_at - started_at;
+        RunSummary {
+            started_at: started_at as u64,
+            ended_at: ended_at as u64,
+            cumulative_nonce,
+            hashes,
+            solutions_found,
+            hashes_per_second: if runtime > 0.0 {
+                hashes as f64 / runtime
+            } else {
+                0.0
+            },
+            zero_byte_histogram,
+            config: ConfigSummary::from(config),
+        }
+    }
+
+    /// Appends the summary to `path` as a single line, so the summaries of
+    /// earlier runs are kept.
+    pub(crate) fn write(&self, path: &str) -> io::Result<()> {
+        let mut json = serde_json::to_string(self)?;
+        json.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(json.as_bytes())
+    }
+}
+
+/// The summary file next to `output_file`, or in the working directory if
+/// solutions go to stdout.
+pub fn default_summary_file(output_file: &str) -> String {
+    if output::is_stdout(output_file) {
+        return "run_summary.jsonl".to_string();
+    }
+    Path::new(output_file)
+        .with_file_name("run_summary.jsonl")
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// How many solutions were found per number of zero bytes in their address,
+/// to calibrate thresholds and rewards against.
+#[derive(Debug, Default, Serialize)]
+#[serde(transparent)]
+pub(crate) struct ZeroHistogram(BTreeMap<usize, u64>);
+
+impl ZeroHistogram {
+    /// The width of the longest bar when printed.
+    const WIDTH: u64 = 40;
+
+    pub(crate) fn record(&mut self, total_zeros: usize) {
+        *self.0.entry(total_zeros).or_default() += 1;
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Display for ZeroHistogram {
+    /// One line per zero byte count that was found, with a bar scaled to the
+    /// most common count.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let max = self.0.values().copied().max().unwrap_or(0);
+        write!(f, "zero bytes | solutions")?;
+        for (total_zeros, &count) in &self.0 {
+            let bar = (count * Self::WIDTH + max - 1) / max;
+            write!(
+                f,
+                "\n{total_zeros:>10} | {count:<9} {}",
+                "#".repeat(bar as usize)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The settings of the search a summary was written for. The notification
+/// command is left out as it may carry credentials.
+#[derive(Serialize)]
+struct ConfigSummary<'a> {
+    factory: Address,
+    owner: Address,
+    init_code_hashes: &'a [FixedBytes<32>],
+    mode: Mode,
+    create3_variant: Create3Variant,
+    salt_prefix: Option<&'a Bytes>,
+    salt_bytes: u8,
+    salt_mode: SaltMode,
+    salt_start: u64,
+    nonce_stride: Option<u32>,
+    work_size: u32,
+    gpu_devices: &'a [u8],
+    max_create3_nonce: u8,
+    proxy_nonces: Option<&'a [u8]>,
+    leading_zeroes_threshold: Option<u8>,
+    total_zeroes_threshold: Option<u8>,
+    leading_zero_nibbles_threshold: Option<u8>,
+    repeat_run: Option<u8>,
+    prefixes: Vec<String>,
+    match_bytes: Vec<String>,
+    checksum_regex: Option<&'a str>,
+    min_uppercase: Option<u8>,
+    address_range: Option<String>,
+    require_all: bool,
+    min_reward_score: Option<f64>,
+    stop_at_reward: Option<&'a str>,
+    output_file: &'a str,
+    output_format: OutputFormat,
+    output_template: Option<&'a str>,
+    tag: Option<&'a str>,
+    seed: Option<u64>,
+}
+
+impl<'a> From<&'a Config> for ConfigSummary<'a> {
+    fn from(config: &'a Config) -> Self {
+        ConfigSummary {
+            factory: config.factory,
+            owner: config.owner,
+            init_code_hashes: &config.init_code_hashes,
+            mode: config.mode,
+            create3_variant: config.create3_variant,
+            salt_prefix: config.salt_prefix.as_ref(),
+            salt_bytes: config.salt_bytes,
+            salt_mode: config.salt_mode,
+            salt_start: config.salt_start,
+            nonce_stride: config.nonce_stride,
+            work_size: config.work_size,
+            gpu_devices: &config.gpu_devices,
+            max_create3_nonce: config.max_create3_nonce,
+            proxy_nonces: config.proxy_nonces.as_deref(),
+            leading_zeroes_threshold: config.leading_zeroes_threshold,
+            total_zeroes_threshold: config.total_zeroes_threshold,
+            leading_zero_nibbles_threshold: config.leading_zero_nibbles_threshold,
+            repeat_run: config.repeat_run,
+            prefixes: config.prefixes.iter().map(ToString::to_string).collect(),
+            match_bytes: config.match_bytes.iter().map(ToString::to_string).collect(),
+            checksum_regex: config.checksum_regex.as_ref().map(|regex| regex.as_str()),
+            min_uppercase: config.min_uppercase,
+            address_range: config.address_range.map(|range| range.to_string()),
+            require_all: config.require_all,
+            min_reward_score: config.min_reward_score,
+            stop_at_reward: config.stop_at_reward.as_deref(),
+            output_file: &config.output_file,
+            output_format: config.output_format,
+            output_template: config
+                .output_template
+                .as_ref()
+                .map(|template| template.as_str()),
+            tag: config.tag.as_deref(),
+            seed: config.seed,
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use crate::{derive_address, Config};
+use alloy_primitives::{hex, Address, FixedBytes};
+use std::fs;
+use std::io;
+
+/// The outcome of checking every line of an output file.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Verification {
+    /// Lines whose salt deploys to the recorded address.
+    pub verified: usize,
+    /// Lines whose salt deploys somewhere else.
+    pub mismatched: usize,
+    /// Lines that couldn't be parsed, e.g. those of a custom output template,
+    /// or that record an impossible nonce.
+    pub skipped: usize,
+}
+
+/// A solution as read back from an output file.
+struct Recorded {
+    salt: [u8; 32],
+    nonce: u64,
+    address: Address,
+    init_code_hash: Option<FixedBytes<32>>,
+}
+
+/// Recomputes the address of every solution in the output file at `path` on
+/// the CPU and compares it against the recorded one, printing every mismatch.
+///
+/// Text and Solidity lines don't record the initcode hash, so they pass if any of the
+/// Config's hashes reproduces the address.
+pub fn verify(config: &Config, path: &str) -> io::Result<Verification> {
+    let contents = fs::read_to_string(path)?;
+    let mut verification = Verification::default();
+
+    for (number, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some(recorded) = parse_line(line) else {
+            verification.skipped += 1;
+            continue;
+        };
+        // no deploy proxy can have deployed past the last nonce
+        let Some(create1_nonce) = recorded.nonce.checked_add(1) else {
+            verification.skipped += 1;
+            continue;
+        };
+
+        let hashes = match &recorded.init_code_hash {
+            Some(hash) => std::slice::from_ref(hash),
+            None => &config.init_code_hashes[..],
+        };
+        let derived: Vec<Address> = hashes
+            .iter()
+            .map(|&hash| derive_address(config, &recorded.salt, create1_nonce, hash))
+            .collect();
+
+        if derived.contains(&recorded.address) {
+            verification.verified += 1;
+        } else {
+            verification.mismatched += 1;
+            println!(
+                "line {}: salt 0x{} ({}) deploys to {}, not {}",
+                number + 1,
+                hex::encode(recorded.salt),
+                recorded.nonce,
+                derived[0],
+                recorded.address
+            );
+        }
+    }
+
+    Ok(verification)
+}
+
+/// Parses a line of any output format, `None` if it's none of them.
+fn parse_line(line: &str) -> Option<Recorded> {
+    if line.starts_with('{') {
+        let value: serde_json::Value = serde_json::from_str(line).ok()?;
+        return Some(Recorded {
+            salt: hex::decode(value.get("salt")?.as_str()?)
+                .ok()?
+                .try_into()
+                .ok()?,
+            nonce: value.get("nonce")?.as_u64()?,
+            address: value.get("address")?.as_str()?.parse().ok()?,
+            init_code_hash: value
+                .get("init_code_hash")
+                .and_then(|hash| hash.as_str()?.parse().ok()),
+        });
+    }
+
+    if let Some(constant) = line.strip_prefix("bytes32 constant ") {
+        // `SALT_{address} = 0x{salt}; // => {address} (nonce {nonce}) => {reward}`
+        let (_, rest) = constant.split_once(" = ")?;
+        let (salt, rest) = rest.split_once("; // => ")?;
+        let (address, rest) = rest.split_once(" (nonce ")?;
+        let (nonce, _) = rest.split_once(')')?;
+        return Some(Recorded {
+            salt: hex::decode(salt).ok()?.try_into().ok()?,
+            nonce: nonce.parse().ok()?,
+            address: address.parse().ok()?,
+            init_code_hash: None,
+        });
+    }
+
+    // `0x{salt} ({nonce}) => {address} => {reward} (proxy {proxy_address})`
+    let mut fields = line.split(" => ");
+    let (salt, nonce) = fields.next()?.split_once(' ')?;
+    let nonce = nonce.strip_prefix('(')?.strip_suffix(')')?;
+    let address = fields.next()?.parse().ok()?;
+    // the proxy is recomputed along with the address, but it has to be there
+    // for the line to be one of ours rather than a custom template's
+    let (_, proxy) = fields.next()?.rsplit_once(" (proxy ")?;
+    proxy.strip_suffix(')')?.parse::<Address>().ok()?;
+    if fields.next().is_some() {
+        return None;
+    }
+    Some(Recorded {
+        salt: hex::decode(salt).ok()?.try_into().ok()?,
+        nonce: nonce.parse().ok()?,
+        address,
+        init_code_hash: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{address, b256};
+
+    const SALT: &str = "000000000000000000000000000000000000dead010203048877665544332211";
+
+    #[test]
+    fn text_lines_are_the_default_line_with_the_proxy() {
+        let line = format!(
+            "0x{SALT} (0) => 0x357FC0E1C0aD569B3aabd4458AbAEDD3981d84ce => 0 \
+             (proxy 0x73f102fa001b366b719378ff2a9024d20b27F4E2)"
+        );
+        let recorded = parse_line(&line).unwrap();
+        assert_eq!(
+            recorded.salt,
+            b256!("000000000000000000000000000000000000dead010203048877665544332211").0
+        );
+        assert_eq!(recorded.nonce, 0);
+        assert_eq!(
+            recorded.address,
+            address!("357FC0E1C0aD569B3aabd4458AbAEDD3981d84ce")
+        );
+        assert!(recorded.init_code_hash.is_none());
+
+        // lines without the proxy are some other template's
+        let line = format!("0x{SALT} (0) => 0x357FC0E1C0aD569B3aabd4458AbAEDD3981d84ce => 0");
+        assert!(parse_line(&line).is_none());
+    }
+}